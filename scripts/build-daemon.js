@@ -0,0 +1,42 @@
+// Builds the session daemon and places it where `bundle.externalBin` expects
+// it: src-tauri/binaries/sci-fi-ui-daemon-<target triple>. Pass --release for
+// an optimised build.
+import { execFileSync } from 'node:child_process'
+import { copyFileSync, mkdirSync } from 'node:fs'
+import { dirname, join } from 'node:path'
+import { fileURLToPath } from 'node:url'
+
+const BINARY = 'sci-fi-ui-daemon'
+const tauriDir = join(dirname(fileURLToPath(import.meta.url)), '..', 'src-tauri')
+const release = process.argv.includes('--release')
+
+const hostTriple = execFileSync('rustc', ['-vV'], { encoding: 'utf8' }).match(/^host: (\S+)$/m)[1]
+// Set by the Tauri CLI when it runs the before-commands.
+const triple = process.env.TAURI_ENV_TARGET_TRIPLE || hostTriple
+const extension = triple.includes('windows') ? '.exe' : ''
+
+const args = ['build', '--bin', BINARY]
+if (release) args.push('--release')
+if (triple !== hostTriple) args.push('--target', triple)
+
+const targetDir = join(tauriDir, 'target', 'daemon')
+execFileSync('cargo', args, {
+	cwd: tauriDir,
+	stdio: 'inherit',
+	env: {
+		...process.env,
+		// Separate from the app's own build, which may be running at the same time.
+		CARGO_TARGET_DIR: targetDir,
+		// tauri-build refuses to run while the sidecar it is building is missing.
+		TAURI_CONFIG: JSON.stringify({ bundle: { externalBin: [] } }),
+	},
+})
+
+const built = join(
+	targetDir,
+	triple === hostTriple ? '' : triple,
+	release ? 'release' : 'debug',
+	BINARY + extension,
+)
+mkdirSync(join(tauriDir, 'binaries'), { recursive: true })
+copyFileSync(built, join(tauriDir, 'binaries', `${BINARY}-${triple}${extension}`))
@@ -0,0 +1,3 @@
+fn main() {
+    rizo_ui_lib::run_daemon()
+}
@@ -1 +1,4 @@
+pub mod monitors;
 pub mod pollinations_ai;
+pub mod shell_profiles;
+pub mod triggers;
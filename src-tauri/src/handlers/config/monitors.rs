@@ -0,0 +1,106 @@
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use std::{collections::HashMap, time::Duration};
+use tauri::State;
+use tauri_plugin_store::StoreExt;
+
+use crate::handlers::events::sampler::Monitor;
+use crate::AppState;
+
+const MONITORS_KEY: &str = "system_monitors";
+/// CPU usage is meaningless when sampled more often than this.
+const MIN_INTERVAL_MS: u64 = 250;
+const MAX_INTERVAL_MS: u64 = 60 * 60 * 1000;
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct MonitorConfig {
+    pub interval_ms: u64,
+    pub enabled: bool,
+}
+
+impl MonitorConfig {
+    pub fn default_for(monitor: Monitor) -> Self {
+        MonitorConfig {
+            interval_ms: monitor.default_interval().as_millis() as u64,
+            enabled: true,
+        }
+    }
+
+    /// How often to sample, or `None` while the monitor is off.
+    pub fn interval(&self) -> Option<Duration> {
+        self.enabled
+            .then(|| Duration::from_millis(self.interval_ms))
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct MonitorStatus {
+    pub name: Monitor,
+    pub interval_ms: u64,
+    pub enabled: bool,
+}
+
+pub fn read_monitor_configs(
+    app: &tauri::AppHandle,
+) -> Result<HashMap<Monitor, MonitorConfig>, String> {
+    let store = app
+        .store("settings.json")
+        .map_err(|e| format!("Failed to access store: {}", e))?;
+
+    match store.get(MONITORS_KEY) {
+        Some(value) => serde_json::from_value(value)
+            .map_err(|e| format!("Invalid monitor settings in store: {}", e)),
+        None => Ok(HashMap::new()),
+    }
+}
+
+fn status(monitor: Monitor, config: MonitorConfig) -> MonitorStatus {
+    MonitorStatus {
+        name: monitor,
+        interval_ms: config.interval_ms,
+        enabled: config.enabled,
+    }
+}
+
+#[tauri::command]
+pub fn monitor_status(state: State<'_, AppState>) -> Vec<MonitorStatus> {
+    Monitor::ALL
+        .into_iter()
+        .map(|monitor| status(monitor, state.monitors.config(monitor)))
+        .collect()
+}
+
+/// Turns a monitor on or off or changes how often it samples; `interval_ms`
+/// keeps the current interval when omitted. Takes effect immediately and is
+/// remembered across restarts.
+#[tauri::command]
+pub fn monitor_configure(
+    app: tauri::AppHandle,
+    name: Monitor,
+    interval_ms: Option<u64>,
+    enabled: bool,
+    state: State<'_, AppState>,
+) -> Result<MonitorStatus, String> {
+    let mut config = state.monitors.config(name);
+    if let Some(interval_ms) = interval_ms {
+        if !(MIN_INTERVAL_MS..=MAX_INTERVAL_MS).contains(&interval_ms) {
+            return Err(format!(
+                "Monitor interval must be between {} and {} ms",
+                MIN_INTERVAL_MS, MAX_INTERVAL_MS
+            ));
+        }
+        config.interval_ms = interval_ms;
+    }
+    config.enabled = enabled;
+
+    let mut configs = state.monitors.configs();
+    configs.insert(name, config);
+    let store = app
+        .store("settings.json")
+        .map_err(|e| format!("Failed to access store: {}", e))?;
+    store.set(MONITORS_KEY, json!(configs));
+    store.save().map_err(|e| e.to_string())?;
+
+    state.monitors.configure(name, config);
+    Ok(status(name, config))
+}
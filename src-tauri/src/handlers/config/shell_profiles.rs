@@ -0,0 +1,142 @@
+use portable_pty::CommandBuilder;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use std::collections::HashMap;
+use tauri_plugin_store::StoreExt;
+
+const PROFILES_KEY: &str = "shell_profiles";
+pub const DEFAULT_PROFILE: &str = "default";
+
+/// A named way to start a terminal session: zsh, `bash --login`, a Python REPL...
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ShellProfile {
+    pub name: String,
+    pub program: String,
+    #[serde(default)]
+    pub args: Vec<String>,
+    #[serde(default)]
+    pub cwd: Option<String>,
+    #[serde(default)]
+    pub env: HashMap<String, String>,
+    /// Load the OSC 133 / OSC 7 hooks into bash and zsh.
+    #[serde(default = "default_true")]
+    pub shell_integration: bool,
+}
+
+fn default_true() -> bool {
+    true
+}
+
+impl ShellProfile {
+    fn builtin() -> Self {
+        #[cfg(target_os = "windows")]
+        let (program, term) = ("powershell.exe", "cygwin");
+
+        #[cfg(not(target_os = "windows"))]
+        let (program, term) = ("bash", "xterm-256color");
+
+        ShellProfile {
+            name: DEFAULT_PROFILE.to_string(),
+            program: program.to_string(),
+            args: Vec::new(),
+            cwd: None,
+            env: HashMap::from([("TERM".to_string(), term.to_string())]),
+            shell_integration: true,
+        }
+    }
+
+    pub fn to_command(&self) -> CommandBuilder {
+        let mut cmd = CommandBuilder::new(&self.program);
+        cmd.args(&self.args);
+
+        #[cfg(not(target_os = "windows"))]
+        cmd.env("TERM", "xterm-256color");
+
+        for (key, value) in &self.env {
+            cmd.env(key, value);
+        }
+
+        if let Some(cwd) = &self.cwd {
+            cmd.cwd(expand_home(cwd));
+        }
+
+        cmd
+    }
+}
+
+fn expand_home(path: &str) -> String {
+    match (path.strip_prefix('~'), std::env::var("HOME")) {
+        (Some(rest), Ok(home)) if rest.is_empty() || rest.starts_with('/') => {
+            format!("{}{}", home, rest)
+        }
+        _ => path.to_string(),
+    }
+}
+
+fn read_profiles(app: &tauri::AppHandle) -> Result<Vec<ShellProfile>, String> {
+    let store = app
+        .store("settings.json")
+        .map_err(|e| format!("Failed to access store: {}", e))?;
+
+    match store.get(PROFILES_KEY) {
+        Some(value) => serde_json::from_value(value)
+            .map_err(|e| format!("Invalid shell profiles in store: {}", e)),
+        None => Ok(Vec::new()),
+    }
+}
+
+fn write_profiles(app: &tauri::AppHandle, profiles: &[ShellProfile]) -> Result<(), String> {
+    let store = app
+        .store("settings.json")
+        .map_err(|e| format!("Failed to access store: {}", e))?;
+
+    store.set(PROFILES_KEY, json!(profiles));
+    store.save().map_err(|e| e.to_string())
+}
+
+/// Looks a profile up by name, falling back to the built-in shell for the default one.
+pub fn load_shell_profile(app: &tauri::AppHandle, name: &str) -> Result<ShellProfile, String> {
+    if let Some(profile) = read_profiles(app)?.into_iter().find(|p| p.name == name) {
+        return Ok(profile);
+    }
+
+    if name == DEFAULT_PROFILE {
+        Ok(ShellProfile::builtin())
+    } else {
+        Err(format!("Unknown shell profile: {}", name))
+    }
+}
+
+#[tauri::command]
+pub fn list_shell_profiles(app: tauri::AppHandle) -> Result<Vec<ShellProfile>, String> {
+    let mut profiles = read_profiles(&app)?;
+    if !profiles.iter().any(|p| p.name == DEFAULT_PROFILE) {
+        profiles.insert(0, ShellProfile::builtin());
+    }
+    Ok(profiles)
+}
+
+/// Adds a profile or replaces the one with the same name.
+#[tauri::command]
+pub fn save_shell_profile(app: tauri::AppHandle, profile: ShellProfile) -> Result<(), String> {
+    if profile.name.trim().is_empty() {
+        return Err("Shell profile name must not be empty".to_string());
+    }
+    if profile.program.trim().is_empty() {
+        return Err("Shell profile program must not be empty".to_string());
+    }
+
+    let mut profiles = read_profiles(&app)?;
+    match profiles.iter_mut().find(|p| p.name == profile.name) {
+        Some(existing) => *existing = profile,
+        None => profiles.push(profile),
+    }
+    write_profiles(&app, &profiles)
+}
+
+#[tauri::command]
+pub fn delete_shell_profile(app: tauri::AppHandle, name: String) -> Result<(), String> {
+    let mut profiles = read_profiles(&app)?;
+    profiles.retain(|p| p.name != name);
+    write_profiles(&app, &profiles)
+}
@@ -0,0 +1,88 @@
+use regex::RegexBuilder;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use tauri::State;
+use tauri_plugin_store::StoreExt;
+
+use crate::AppState;
+
+const TRIGGERS_KEY: &str = "terminal_triggers";
+const DEFAULT_COOLDOWN_MS: u64 = 5000;
+
+/// A regex matched against each line of terminal output; a hit emits `terminal_trigger`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TriggerRule {
+    pub name: String,
+    pub pattern: String,
+    #[serde(default)]
+    pub case_sensitive: bool,
+    /// Shell profiles whose sessions the rule watches; empty means all of them.
+    #[serde(default)]
+    pub profiles: Vec<String>,
+    /// Phrase for the widget to speak through `generate_audio` when the rule fires.
+    #[serde(default)]
+    pub speak: Option<String>,
+    #[serde(default)]
+    pub voice: Option<String>,
+    /// Minimum time between two hits of this rule in the same session.
+    #[serde(default = "default_cooldown_ms")]
+    pub cooldown_ms: u64,
+}
+
+fn default_cooldown_ms() -> u64 {
+    DEFAULT_COOLDOWN_MS
+}
+
+impl TriggerRule {
+    pub fn applies_to(&self, profile: &str) -> bool {
+        self.profiles.is_empty() || self.profiles.iter().any(|p| p == profile)
+    }
+
+    pub fn regex(&self) -> Result<regex::Regex, String> {
+        RegexBuilder::new(&self.pattern)
+            .case_insensitive(!self.case_sensitive)
+            .build()
+            .map_err(|err| format!("Invalid pattern in trigger {}: {}", self.name, err))
+    }
+}
+
+pub fn read_trigger_rules(app: &tauri::AppHandle) -> Result<Vec<TriggerRule>, String> {
+    let store = app
+        .store("settings.json")
+        .map_err(|e| format!("Failed to access store: {}", e))?;
+
+    match store.get(TRIGGERS_KEY) {
+        Some(value) => serde_json::from_value(value)
+            .map_err(|e| format!("Invalid terminal triggers in store: {}", e)),
+        None => Ok(Vec::new()),
+    }
+}
+
+#[tauri::command]
+pub fn list_trigger_rules(app: tauri::AppHandle) -> Result<Vec<TriggerRule>, String> {
+    read_trigger_rules(&app)
+}
+
+/// Replaces every rule at once; running sessions pick the new rules up immediately.
+#[tauri::command]
+pub async fn save_trigger_rules(
+    app: tauri::AppHandle,
+    rules: Vec<TriggerRule>,
+    state: State<'_, AppState>,
+) -> Result<(), String> {
+    for rule in &rules {
+        if rule.name.trim().is_empty() {
+            return Err("Trigger name must not be empty".to_string());
+        }
+        rule.regex()?;
+    }
+
+    let store = app
+        .store("settings.json")
+        .map_err(|e| format!("Failed to access store: {}", e))?;
+    store.set(TRIGGERS_KEY, json!(rules));
+    store.save().map_err(|e| e.to_string())?;
+
+    state.sessions.reload_triggers(&rules).await;
+    Ok(())
+}
@@ -0,0 +1,68 @@
+use std::{collections::HashMap, fs, time::Instant};
+
+use super::DiskIo;
+
+/// `/proc/diskstats` counts in 512-byte sectors whatever the device's own sector size.
+const SECTOR_SIZE: u64 = 512;
+
+/// Completed reads, sectors read, completed writes and sectors written.
+type Counters = [u64; 4];
+
+fn read_counters() -> Option<HashMap<String, Counters>> {
+    let stats = fs::read_to_string("/proc/diskstats").ok()?;
+    let mut devices = HashMap::new();
+    for line in stats.lines() {
+        let fields: Vec<&str> = line.split_whitespace().collect();
+        if fields.len() < 10 {
+            continue;
+        }
+        let field = |i: usize| fields[i].parse::<u64>().unwrap_or(0);
+        devices.insert(
+            fields[2].to_string(),
+            [field(3), field(5), field(7), field(9)],
+        );
+    }
+    Some(devices)
+}
+
+/// Per-device I/O rates between two reads of `/proc/diskstats`.
+#[derive(Default)]
+pub struct DiskStatsReader {
+    previous: Option<(Instant, HashMap<String, Counters>)>,
+}
+
+impl DiskStatsReader {
+    /// Rates since the previous call, keyed by kernel device name (`sda1`,
+    /// `nvme0n1p2`, `dm-0`). Empty on the first call and where
+    /// `/proc/diskstats` does not exist.
+    pub fn read(&mut self) -> HashMap<String, DiskIo> {
+        let Some(current) = read_counters() else {
+            return HashMap::new();
+        };
+        let now = Instant::now();
+        let Some((then, previous)) = self.previous.replace((now, current.clone())) else {
+            return HashMap::new();
+        };
+        let seconds = now.duration_since(then).as_secs_f64();
+        if seconds <= 0.0 {
+            return HashMap::new();
+        }
+
+        current
+            .into_iter()
+            .filter_map(|(device, counters)| {
+                let before = previous.get(&device)?;
+                let rate = |i: usize| counters[i].saturating_sub(before[i]) as f64 / seconds;
+                Some((
+                    device,
+                    DiskIo {
+                        read_bytes_per_sec: (rate(1) * SECTOR_SIZE as f64) as u64,
+                        write_bytes_per_sec: (rate(3) * SECTOR_SIZE as f64) as u64,
+                        read_iops: rate(0),
+                        write_iops: rate(2),
+                    },
+                ))
+            })
+            .collect()
+    }
+}
@@ -0,0 +1,195 @@
+use serde::Serialize;
+use std::{
+    collections::{HashMap, VecDeque},
+    sync::Mutex,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use super::sampler::Snapshot;
+
+/// Bucket width and number of buckets kept, finest first: 1 s for 10 minutes,
+/// 10 s for 6 hours and 1 min for 7 days.
+const TIERS: [(u64, usize); 3] = [(1_000, 600), (10_000, 2_160), (60_000, 10_080)];
+
+/// Metrics recorded from the sampler's snapshots.
+pub const METRICS: [&str; 3] = ["cpu", "memory", "ping"];
+
+/// One bucket of samples. `time` is where the bucket starts, in Unix milliseconds.
+#[derive(Debug, Clone, Copy, Serialize)]
+pub struct MetricPoint {
+    pub time: u64,
+    pub value: f64,
+    pub min: f64,
+    pub max: f64,
+}
+
+#[derive(Debug, Serialize)]
+pub struct MetricSeries {
+    pub metric: String,
+    pub resolution_ms: u64,
+    pub points: Vec<MetricPoint>,
+}
+
+struct Bucket {
+    start: u64,
+    sum: f64,
+    count: u32,
+    min: f64,
+    max: f64,
+}
+
+impl Bucket {
+    fn point(&self) -> MetricPoint {
+        MetricPoint {
+            time: self.start,
+            value: self.sum / self.count as f64,
+            min: self.min,
+            max: self.max,
+        }
+    }
+}
+
+/// A ring of fixed-width buckets. Every tier averages the raw samples itself
+/// rather than the finer tier's buckets.
+struct Tier {
+    resolution_ms: u64,
+    capacity: usize,
+    points: VecDeque<MetricPoint>,
+    /// The bucket still filling up; it is included in queries all the same.
+    pending: Option<Bucket>,
+}
+
+impl Tier {
+    fn new(resolution_ms: u64, capacity: usize) -> Self {
+        Tier {
+            resolution_ms,
+            capacity,
+            points: VecDeque::new(),
+            pending: None,
+        }
+    }
+
+    fn retention_ms(&self) -> u64 {
+        self.resolution_ms * self.capacity as u64
+    }
+
+    fn push(&mut self, time: u64, value: f64) {
+        let start = time - time % self.resolution_ms;
+        match &mut self.pending {
+            Some(bucket) if bucket.start == start => {
+                bucket.sum += value;
+                bucket.count += 1;
+                bucket.min = bucket.min.min(value);
+                bucket.max = bucket.max.max(value);
+                return;
+            }
+            Some(bucket) => {
+                let point = bucket.point();
+                if self.points.len() == self.capacity {
+                    self.points.pop_front();
+                }
+                self.points.push_back(point);
+            }
+            None => {}
+        }
+        self.pending = Some(Bucket {
+            start,
+            sum: value,
+            count: 1,
+            min: value,
+            max: value,
+        });
+    }
+
+    fn range(&self, from: u64, to: u64) -> Vec<MetricPoint> {
+        self.points
+            .iter()
+            .copied()
+            .chain(self.pending.as_ref().map(Bucket::point))
+            .filter(|point| point.time + self.resolution_ms > from && point.time <= to)
+            .collect()
+    }
+}
+
+struct Series {
+    tiers: Vec<Tier>,
+}
+
+impl Series {
+    fn new() -> Self {
+        Series {
+            tiers: TIERS
+                .iter()
+                .map(|&(resolution_ms, capacity)| Tier::new(resolution_ms, capacity))
+                .collect(),
+        }
+    }
+}
+
+fn now_millis() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_millis() as u64)
+        .unwrap_or(0)
+}
+
+/// Recent values of every metric, so charts mounted later can backfill.
+pub struct MetricHistory {
+    series: Mutex<HashMap<&'static str, Series>>,
+}
+
+impl MetricHistory {
+    pub fn new() -> Self {
+        MetricHistory {
+            series: Mutex::new(METRICS.iter().map(|&name| (name, Series::new())).collect()),
+        }
+    }
+
+    fn push(&self, metric: &'static str, time: u64, value: f64) {
+        let mut series = self.series.lock().unwrap();
+        let series = series.entry(metric).or_insert_with(Series::new);
+        for tier in &mut series.tiers {
+            tier.push(time, value);
+        }
+    }
+
+    pub fn record(&self, snapshot: &Snapshot) {
+        let now = now_millis();
+        if let Some(cpu) = &snapshot.cpu {
+            self.push("cpu", now, cpu.usage as f64);
+        }
+        if let Some(memory) = &snapshot.memory {
+            self.push("memory", now, memory.percentage);
+        }
+        if let Some(ping) = &snapshot.ping {
+            self.push("ping", now, ping.latency as f64);
+        }
+    }
+
+    /// Points of `metric` between `from` and `to` (Unix milliseconds) from the
+    /// finest tier that is at least `resolution_ms` wide and still reaches back
+    /// to `from`; the coarsest tier when none does.
+    pub fn query(
+        &self,
+        metric: &str,
+        from: u64,
+        to: u64,
+        resolution_ms: Option<u64>,
+    ) -> Option<MetricSeries> {
+        let series = self.series.lock().unwrap();
+        let series = series.get(metric)?;
+        let span = now_millis().saturating_sub(from);
+        let tier = series
+            .tiers
+            .iter()
+            .filter(|tier| tier.resolution_ms >= resolution_ms.unwrap_or(0))
+            .find(|tier| tier.retention_ms() >= span)
+            .or(series.tiers.last())?;
+
+        Some(MetricSeries {
+            metric: metric.to_string(),
+            resolution_ms: tier.resolution_ms,
+            points: tier.range(from, to),
+        })
+    }
+}
@@ -0,0 +1,243 @@
+pub mod diskstats;
+pub mod metrics;
+pub mod ping;
+pub mod proc_stat;
+pub mod sampler;
+
+use serde::Serialize;
+use std::{
+    collections::{HashMap, HashSet},
+    sync::{Arc, Mutex},
+    thread,
+    time::Duration,
+};
+use tauri::{AppHandle, Emitter, State};
+
+use crate::handlers::config::monitors::MonitorConfig;
+use crate::AppState;
+use metrics::{MetricHistory, MetricSeries};
+use sampler::{Monitor, Sampler, SamplerControl, Snapshot};
+
+#[derive(Serialize, Clone)]
+pub struct CpuUsage {
+    pub usage: f32,
+    pub cores: Vec<CoreUsage>,
+    pub load_average: LoadAverage,
+    /// Only available on Linux, and from the second sample on.
+    pub times: Option<CpuTimes>,
+}
+
+#[derive(Serialize, Clone)]
+pub struct CoreUsage {
+    pub name: String,
+    pub usage: f32,
+    /// In MHz.
+    pub frequency: u64,
+}
+
+#[derive(Serialize, Clone)]
+pub struct LoadAverage {
+    pub one: f64,
+    pub five: f64,
+    pub fifteen: f64,
+}
+
+/// Percent of CPU time since the previous sample. `user` includes niced
+/// processes and `system` includes interrupt handling.
+#[derive(Serialize, Clone)]
+pub struct CpuTimes {
+    pub user: f32,
+    pub system: f32,
+    pub iowait: f32,
+    pub steal: f32,
+}
+
+#[derive(Serialize, Clone)]
+pub struct MemoryUsage {
+    pub used: u64,
+    pub total: u64,
+    pub percentage: f64,
+}
+
+#[derive(Serialize, Clone)]
+pub struct ProcessInfo {
+    pub name: String,
+    pub pid: u32,
+    pub cpu: f32,
+    pub memory: u64,
+}
+
+#[derive(Serialize, Clone)]
+pub struct PingResult {
+    pub latency: u64,
+    pub status: String,
+}
+
+#[derive(Serialize, Clone)]
+pub struct DiskInfo {
+    pub device: String,
+    pub mount_point: String,
+    pub file_system: String,
+    pub total: u64,
+    /// Space available to unprivileged users.
+    pub free: u64,
+    pub used_percentage: f64,
+    pub nearly_full: bool,
+    pub critical: bool,
+    /// Only available on Linux, and from the second sample on.
+    pub io: Option<DiskIo>,
+}
+
+#[derive(Serialize, Clone)]
+pub struct DiskIo {
+    pub read_bytes_per_sec: u64,
+    pub write_bytes_per_sec: u64,
+    pub read_iops: f64,
+    pub write_iops: f64,
+}
+
+/// Sends each part of a snapshot to the widgets as its own `*_update` event.
+fn emit_snapshot(app: &AppHandle, snapshot: &Snapshot) {
+    if let Some(cpu) = &snapshot.cpu {
+        let _ = app.emit("cpu_update", cpu);
+    }
+    if let Some(memory) = &snapshot.memory {
+        let _ = app.emit("memory_update", memory);
+    }
+    if let Some(processes) = &snapshot.processes {
+        let _ = app.emit("process_update", processes);
+    }
+    if let Some(ping) = &snapshot.ping {
+        let _ = app.emit("ping_update", ping);
+    }
+    if let Some(disks) = &snapshot.disks {
+        let _ = app.emit("disk_update", disks);
+    }
+}
+
+/// The shared sampler behind the CPU, memory, process, ping and disk widgets, and
+/// the configuration it currently runs with.
+pub struct Monitors {
+    control: SamplerControl,
+    /// Held until [`Monitors::start`] moves it onto its thread.
+    sampler: Mutex<Option<Sampler>>,
+    configs: Mutex<HashMap<Monitor, MonitorConfig>>,
+    /// Monitors a widget on screen shows. Not saved: this is what the UI
+    /// needs right now, while `configs` is what the user chose.
+    held: Mutex<HashSet<Monitor>>,
+    history: Arc<MetricHistory>,
+}
+
+impl Monitors {
+    pub fn new() -> Self {
+        let sampler = Sampler::new();
+        Monitors {
+            control: sampler.control(),
+            sampler: Mutex::new(Some(sampler)),
+            configs: Mutex::new(
+                Monitor::ALL
+                    .into_iter()
+                    .map(|monitor| (monitor, MonitorConfig::default_for(monitor)))
+                    .collect(),
+            ),
+            held: Mutex::new(HashSet::new()),
+            history: Arc::new(MetricHistory::new()),
+        }
+    }
+
+    /// How often `monitor` samples: only while enabled and, where it needs
+    /// one, held by a widget.
+    fn interval(&self, held: &HashSet<Monitor>, monitor: Monitor) -> Option<Duration> {
+        if monitor.needs_holder() && !held.contains(&monitor) {
+            return None;
+        }
+        self.config(monitor).interval()
+    }
+
+    /// Starts sampling with `saved` settings; monitors missing from it use their defaults.
+    pub fn start(&self, app: AppHandle, saved: &HashMap<Monitor, MonitorConfig>) {
+        let Some(mut sampler) = self.sampler.lock().unwrap().take() else {
+            return;
+        };
+        {
+            let mut configs = self.configs.lock().unwrap();
+            for monitor in Monitor::ALL {
+                let config = saved.get(&monitor).copied().unwrap_or(configs[&monitor]);
+                configs.insert(monitor, config);
+            }
+        }
+        let held = self.held.lock().unwrap();
+        for monitor in Monitor::ALL {
+            if let Some(interval) = self.interval(&held, monitor) {
+                sampler.schedule(monitor, interval);
+            }
+        }
+        drop(held);
+        let history = self.history.clone();
+        sampler.subscribe(Box::new(move |snapshot| history.record(snapshot)));
+        sampler.subscribe(Box::new(move |snapshot| emit_snapshot(&app, snapshot)));
+        thread::spawn(move || sampler.run());
+    }
+
+    pub fn config(&self, monitor: Monitor) -> MonitorConfig {
+        self.configs.lock().unwrap()[&monitor]
+    }
+
+    pub fn configs(&self) -> HashMap<Monitor, MonitorConfig> {
+        self.configs.lock().unwrap().clone()
+    }
+
+    /// Applies `config` to the running sampler right away.
+    pub fn configure(&self, monitor: Monitor, config: MonitorConfig) {
+        self.configs.lock().unwrap().insert(monitor, config);
+        let held = self.held.lock().unwrap();
+        self.control
+            .configure(monitor, self.interval(&held, monitor));
+    }
+
+    /// Marks whether a widget shows `monitor`, starting or stopping its sampling
+    /// if the user has it enabled.
+    pub fn set_held(&self, monitor: Monitor, is_held: bool) {
+        let mut held = self.held.lock().unwrap();
+        let changed = if is_held {
+            held.insert(monitor)
+        } else {
+            held.remove(&monitor)
+        };
+        if changed {
+            self.control
+                .configure(monitor, self.interval(&held, monitor));
+        }
+    }
+}
+
+/// Tells the backend a widget now shows `name`. Runtime only, unlike
+/// `monitor_configure`: a held monitor the user disabled stays off.
+#[tauri::command]
+pub fn monitor_hold(name: Monitor, state: State<'_, AppState>) {
+    state.monitors.set_held(name, true);
+}
+
+/// Undoes `monitor_hold` once no widget shows `name` any more.
+#[tauri::command]
+pub fn monitor_release(name: Monitor, state: State<'_, AppState>) {
+    state.monitors.set_held(name, false);
+}
+
+/// Recorded values of `metric` between `from` and `to` (Unix milliseconds), so
+/// a chart can show the recent past as soon as it mounts. `resolution` is the
+/// narrowest bucket wanted, in milliseconds.
+#[tauri::command]
+pub fn query_metrics(
+    metric: String,
+    from: u64,
+    to: u64,
+    resolution: Option<u64>,
+    state: State<'_, AppState>,
+) -> Result<MetricSeries, String> {
+    state
+        .monitors
+        .history
+        .query(&metric, from, to, resolution)
+        .ok_or_else(|| format!("Unknown metric: {}", metric))
+}
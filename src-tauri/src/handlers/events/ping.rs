@@ -0,0 +1,89 @@
+use std::process::Command;
+use std::sync::mpsc::{self, SyncSender};
+use std::thread;
+
+use super::PingResult;
+
+impl PingResult {
+    fn from_latency(latency: u64) -> Self {
+        let status = match latency {
+            0 => "offline",
+            1..=50 => "excellent",
+            51..=100 => "good",
+            101..=200 => "average",
+            201..=500 => "poor",
+            _ => "timeout",
+        };
+        PingResult {
+            latency,
+            status: status.to_string(),
+        }
+    }
+}
+
+/// Pings on a thread of its own, once per `()` received, so a slow reply never
+/// holds up the other monitors. Stops when the returned sender is dropped.
+pub fn spawn_worker(on_result: impl Fn(PingResult) + Send + 'static) -> SyncSender<()> {
+    // One request can wait while a ping is in flight; further ones are dropped.
+    let (requests, pending) = mpsc::sync_channel(1);
+    thread::spawn(move || {
+        let ping_address = if cfg!(target_os = "windows") {
+            "127.0.0.1"
+        } else {
+            "8.8.8.8"
+        };
+        for () in pending {
+            on_result(PingResult::from_latency(ping_host(ping_address)));
+        }
+    });
+    requests
+}
+
+fn ping_host(address: &str) -> u64 {
+    let timeout_ms = 1000;
+
+    if cfg!(target_os = "windows") {
+        let output = Command::new("ping")
+            .args(&["-n", "1", "-w", &timeout_ms.to_string(), address])
+            .output();
+
+        match output {
+            Ok(output) if output.status.success() => {
+                let output_str = String::from_utf8_lossy(&output.stdout);
+                output_str
+                    .lines()
+                    .find(|line| line.contains("time="))
+                    .and_then(|line| {
+                        line.split("time=")
+                            .nth(1)
+                            .and_then(|part| part.split('m').next())
+                            .and_then(|time| time.parse::<u64>().ok())
+                    })
+                    .unwrap_or(0)
+            }
+            _ => 0,
+        }
+    } else {
+        let output = Command::new("ping")
+            .args(&["-c", "1", "-W", &(timeout_ms / 1000).to_string(), address])
+            .output();
+
+        match output {
+            Ok(output) if output.status.success() => {
+                let output_str = String::from_utf8_lossy(&output.stdout);
+                output_str
+                    .lines()
+                    .find(|line| line.contains("time="))
+                    .and_then(|line| {
+                        line.split("time=")
+                            .nth(1)
+                            .and_then(|part| part.split(' ').next())
+                            .and_then(|time| time.parse::<f64>().ok())
+                            .map(|time| time.round() as u64)
+                    })
+                    .unwrap_or(0)
+            }
+            _ => 0,
+        }
+    }
+}
@@ -0,0 +1,50 @@
+use std::fs;
+
+use super::CpuTimes;
+
+/// Jiffies from the aggregate `cpu` line of `/proc/stat`, in kernel order:
+/// user, nice, system, idle, iowait, irq, softirq, steal.
+type Jiffies = [u64; 8];
+
+fn read_jiffies() -> Option<Jiffies> {
+    let stat = fs::read_to_string("/proc/stat").ok()?;
+    let line = stat.lines().find(|line| line.starts_with("cpu "))?;
+    let mut jiffies = [0u64; 8];
+    let mut fields = line.split_whitespace().skip(1);
+    for slot in &mut jiffies {
+        *slot = fields.next()?.parse().ok()?;
+    }
+    Some(jiffies)
+}
+
+/// Where CPU time went between two reads of `/proc/stat`.
+#[derive(Default)]
+pub struct CpuTimesReader {
+    previous: Option<Jiffies>,
+}
+
+impl CpuTimesReader {
+    /// Shares of the time since the previous call, in percent. `None` on the
+    /// first call and where `/proc/stat` does not exist.
+    pub fn read(&mut self) -> Option<CpuTimes> {
+        let current = read_jiffies()?;
+        let previous = self.previous.replace(current)?;
+
+        let mut delta = [0u64; 8];
+        for (i, slot) in delta.iter_mut().enumerate() {
+            *slot = current[i].saturating_sub(previous[i]);
+        }
+        let total: u64 = delta.iter().sum();
+        if total == 0 {
+            return None;
+        }
+        let percent = |jiffies: u64| (jiffies as f64 / total as f64 * 100.0) as f32;
+        let [user, nice, system, _idle, iowait, irq, softirq, steal] = delta;
+        Some(CpuTimes {
+            user: percent(user + nice),
+            system: percent(system + irq + softirq),
+            iowait: percent(iowait),
+            steal: percent(steal),
+        })
+    }
+}
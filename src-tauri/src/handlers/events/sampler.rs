@@ -0,0 +1,350 @@
+use serde::{Deserialize, Serialize};
+use std::{
+    fs,
+    path::Path,
+    sync::mpsc::{self, Receiver, RecvTimeoutError, Sender, SyncSender},
+    time::{Duration, Instant},
+};
+use sysinfo::{
+    CpuRefreshKind, DiskRefreshKind, Disks, MemoryRefreshKind, ProcessRefreshKind,
+    ProcessesToUpdate, System,
+};
+
+use super::{
+    diskstats::DiskStatsReader, ping, proc_stat::CpuTimesReader, CoreUsage, CpuUsage, DiskInfo,
+    LoadAverage, MemoryUsage, PingResult, ProcessInfo,
+};
+
+/// Processes reported per sample, busiest first; the widget shows only the top few.
+const MAX_PROCESSES: usize = 50;
+/// Used space, in percent, from which a volume is flagged `nearly_full` and `critical`.
+const NEARLY_FULL_PERCENT: f64 = 90.0;
+const CRITICAL_PERCENT: f64 = 97.0;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Monitor {
+    Cpu,
+    Memory,
+    Process,
+    Ping,
+    Disk,
+}
+
+impl Monitor {
+    pub const ALL: [Monitor; 5] = [
+        Monitor::Cpu,
+        Monitor::Memory,
+        Monitor::Process,
+        Monitor::Ping,
+        Monitor::Disk,
+    ];
+
+    /// Whether the monitor samples only while a widget holds it (`monitor_hold`).
+    /// No widget shows disk usage yet; it runs whenever enabled so
+    /// `query_metrics` has disk history to offer.
+    pub fn needs_holder(self) -> bool {
+        self != Monitor::Disk
+    }
+
+    pub fn default_interval(self) -> Duration {
+        match self {
+            Monitor::Process | Monitor::Disk => Duration::from_secs(2),
+            _ => Duration::from_secs(1),
+        }
+    }
+}
+
+/// What one sampling pass read. Only the monitors that were due are filled in.
+#[derive(Default)]
+pub struct Snapshot {
+    pub cpu: Option<CpuUsage>,
+    pub memory: Option<MemoryUsage>,
+    pub processes: Option<Vec<ProcessInfo>>,
+    pub ping: Option<PingResult>,
+    pub disks: Option<Vec<DiskInfo>>,
+}
+
+impl Snapshot {
+    fn is_empty(&self) -> bool {
+        self.cpu.is_none()
+            && self.memory.is_none()
+            && self.processes.is_none()
+            && self.ping.is_none()
+            && self.disks.is_none()
+    }
+}
+
+pub type Consumer = Box<dyn FnMut(&Snapshot) + Send>;
+
+/// Results and settings changes that arrive between sampling passes.
+enum Message {
+    Ping(PingResult),
+    Configure {
+        monitor: Monitor,
+        interval: Option<Duration>,
+    },
+}
+
+/// Reconfigures a running [`Sampler`] from other threads.
+#[derive(Clone)]
+pub struct SamplerControl(Sender<Message>);
+
+impl SamplerControl {
+    /// Samples `monitor` every `interval` from now on, or stops sampling it on `None`.
+    pub fn configure(&self, monitor: Monitor, interval: Option<Duration>) {
+        let _ = self.0.send(Message::Configure { monitor, interval });
+    }
+}
+
+struct Schedule {
+    monitor: Monitor,
+    interval: Duration,
+    next_due: Instant,
+}
+
+/// The name `/proc/diskstats` uses for a device node, following symlinks
+/// such as `/dev/mapper/root` to `dm-0`.
+fn kernel_device_name(device: &str) -> Option<String> {
+    if !device.starts_with("/dev/") {
+        return None;
+    }
+    let path = fs::canonicalize(device).unwrap_or_else(|_| Path::new(device).to_path_buf());
+    Some(path.file_name()?.to_string_lossy().into_owned())
+}
+
+/// One `System` shared by every monitor. Each pass refreshes only the parts
+/// of it that the monitors due at that moment read.
+pub struct Sampler {
+    system: System,
+    cpu_times: CpuTimesReader,
+    disks: Disks,
+    disk_stats: DiskStatsReader,
+    schedules: Vec<Schedule>,
+    consumers: Vec<Consumer>,
+    sender: Sender<Message>,
+    messages: Receiver<Message>,
+    ping: Option<SyncSender<()>>,
+}
+
+impl Sampler {
+    pub fn new() -> Self {
+        let (sender, messages) = mpsc::channel();
+        Sampler {
+            system: System::new(),
+            cpu_times: CpuTimesReader::default(),
+            disks: Disks::new(),
+            disk_stats: DiskStatsReader::default(),
+            schedules: Vec::new(),
+            consumers: Vec::new(),
+            sender,
+            messages,
+            ping: None,
+        }
+    }
+
+    /// Samples `monitor` every `interval`, starting with the next pass.
+    pub fn schedule(&mut self, monitor: Monitor, interval: Duration) {
+        self.schedules
+            .retain(|schedule| schedule.monitor != monitor);
+        self.schedules.push(Schedule {
+            monitor,
+            interval,
+            next_due: Instant::now(),
+        });
+    }
+
+    /// Stops sampling `monitor`. Disabling ping also ends its worker once the
+    /// ping in flight returns.
+    pub fn unschedule(&mut self, monitor: Monitor) {
+        self.schedules
+            .retain(|schedule| schedule.monitor != monitor);
+        if monitor == Monitor::Ping {
+            self.ping = None;
+        }
+    }
+
+    fn is_scheduled(&self, monitor: Monitor) -> bool {
+        self.schedules
+            .iter()
+            .any(|schedule| schedule.monitor == monitor)
+    }
+
+    pub fn control(&self) -> SamplerControl {
+        SamplerControl(self.sender.clone())
+    }
+
+    pub fn subscribe(&mut self, consumer: Consumer) {
+        self.consumers.push(consumer);
+    }
+
+    fn publish(&mut self, snapshot: &Snapshot) {
+        if snapshot.is_empty() {
+            return;
+        }
+        for consumer in &mut self.consumers {
+            consumer(snapshot);
+        }
+    }
+
+    fn request_ping(&mut self) {
+        let sender = self.sender.clone();
+        let ping = self.ping.get_or_insert_with(|| {
+            ping::spawn_worker(move |result| {
+                let _ = sender.send(Message::Ping(result));
+            })
+        });
+        let _ = ping.try_send(());
+    }
+
+    fn sample_cpu(&mut self) -> CpuUsage {
+        self.system
+            .refresh_cpu_specifics(CpuRefreshKind::nothing().with_cpu_usage().with_frequency());
+        let load = System::load_average();
+        CpuUsage {
+            usage: self.system.global_cpu_usage(),
+            cores: self
+                .system
+                .cpus()
+                .iter()
+                .map(|cpu| CoreUsage {
+                    name: cpu.name().to_string(),
+                    usage: cpu.cpu_usage(),
+                    frequency: cpu.frequency(),
+                })
+                .collect(),
+            load_average: LoadAverage {
+                one: load.one,
+                five: load.five,
+                fifteen: load.fifteen,
+            },
+            times: self.cpu_times.read(),
+        }
+    }
+
+    fn sample_disks(&mut self) -> Vec<DiskInfo> {
+        // Also picks up volumes mounted since the last pass.
+        self.disks
+            .refresh_specifics(true, DiskRefreshKind::nothing().with_storage());
+        let io = self.disk_stats.read();
+        self.disks
+            .list()
+            .iter()
+            .map(|disk| {
+                let total = disk.total_space();
+                let free = disk.available_space();
+                let used_percentage = if total > 0 {
+                    (total.saturating_sub(free) as f64 / total as f64) * 100.0
+                } else {
+                    0.0
+                };
+                DiskInfo {
+                    device: disk.name().to_string_lossy().into_owned(),
+                    mount_point: disk.mount_point().to_string_lossy().into_owned(),
+                    file_system: disk.file_system().to_string_lossy().into_owned(),
+                    total,
+                    free,
+                    used_percentage,
+                    nearly_full: used_percentage >= NEARLY_FULL_PERCENT,
+                    critical: used_percentage >= CRITICAL_PERCENT,
+                    io: kernel_device_name(&disk.name().to_string_lossy())
+                        .and_then(|device| io.get(&device).cloned()),
+                }
+            })
+            .collect()
+    }
+
+    fn sample(&mut self, due: &[Monitor]) -> Snapshot {
+        let mut snapshot = Snapshot::default();
+        for &monitor in due {
+            match monitor {
+                Monitor::Cpu => snapshot.cpu = Some(self.sample_cpu()),
+                Monitor::Memory => {
+                    self.system
+                        .refresh_memory_specifics(MemoryRefreshKind::nothing().with_ram());
+                    let total = self.system.total_memory();
+                    let used = self.system.used_memory();
+                    snapshot.memory = Some(MemoryUsage {
+                        used,
+                        total,
+                        percentage: if total > 0 {
+                            (used as f64 / total as f64) * 100.0
+                        } else {
+                            0.0
+                        },
+                    });
+                }
+                Monitor::Process => {
+                    self.system.refresh_processes_specifics(
+                        ProcessesToUpdate::All,
+                        true,
+                        ProcessRefreshKind::nothing().with_cpu().with_memory(),
+                    );
+                    let mut processes: Vec<ProcessInfo> = self
+                        .system
+                        .processes()
+                        .values()
+                        .map(|p| ProcessInfo {
+                            name: p.name().to_string_lossy().into_owned(),
+                            pid: p.pid().as_u32(),
+                            cpu: p.cpu_usage(),
+                            memory: p.memory(),
+                        })
+                        .collect();
+                    processes.sort_by(|a, b| b.cpu.total_cmp(&a.cpu));
+                    processes.truncate(MAX_PROCESSES);
+                    snapshot.processes = Some(processes);
+                }
+                Monitor::Disk => snapshot.disks = Some(self.sample_disks()),
+                // The result comes back as a message once the ping returns.
+                Monitor::Ping => self.request_ping(),
+            }
+        }
+        snapshot
+    }
+
+    /// Samples whatever is due, sleeping in between until the next monitor is.
+    /// With every monitor disabled it just waits for the next [`SamplerControl`] call.
+    pub fn run(mut self) {
+        loop {
+            let now = Instant::now();
+            let due: Vec<Monitor> = self
+                .schedules
+                .iter_mut()
+                .filter(|schedule| schedule.next_due <= now)
+                .map(|schedule| {
+                    schedule.next_due = now + schedule.interval;
+                    schedule.monitor
+                })
+                .collect();
+            if !due.is_empty() {
+                let snapshot = self.sample(&due);
+                self.publish(&snapshot);
+            }
+
+            let message = match self.schedules.iter().map(|s| s.next_due).min() {
+                Some(next_due) => self
+                    .messages
+                    .recv_timeout(next_due.saturating_duration_since(Instant::now())),
+                None => self
+                    .messages
+                    .recv()
+                    .map_err(|_| RecvTimeoutError::Disconnected),
+            };
+            match message {
+                // A reply to a ping sent before the monitor was disabled.
+                Ok(Message::Ping(_)) if !self.is_scheduled(Monitor::Ping) => {}
+                Ok(Message::Ping(result)) => self.publish(&Snapshot {
+                    ping: Some(result),
+                    ..Default::default()
+                }),
+                Ok(Message::Configure { monitor, interval }) => match interval {
+                    Some(interval) => self.schedule(monitor, interval),
+                    None => self.unschedule(monitor),
+                },
+                Err(RecvTimeoutError::Timeout) => {}
+                Err(RecvTimeoutError::Disconnected) => return,
+            }
+        }
+    }
+}
@@ -0,0 +1,326 @@
+use serde::{Deserialize, Serialize};
+use std::{collections::HashMap, process::Stdio, sync::Mutex, time::Duration};
+use tauri::{
+    async_runtime::{self, JoinHandle},
+    AppHandle, Emitter, State,
+};
+use tokio::{
+    io::{AsyncRead, AsyncReadExt},
+    process::{Child, Command},
+    sync::oneshot,
+};
+
+use crate::handlers::term::decoder::{OutputDecoder, OutputEncoding, PtyData};
+use crate::AppState;
+
+const READ_BUFFER_SIZE: usize = 8 * 1024;
+/// Output kept for the command's result; everything is still streamed as `exec_output`.
+const MAX_CAPTURED_BYTES: usize = 1024 * 1024;
+/// How long to wait for the pipes to close once the process is gone; a
+/// backgrounded grandchild may hold them open indefinitely.
+const DRAIN_TIMEOUT: Duration = Duration::from_millis(500);
+
+pub type JobId = String;
+
+/// Cancellation handles of the commands still running.
+#[derive(Default)]
+pub struct ExecJobs {
+    cancels: Mutex<HashMap<JobId, oneshot::Sender<()>>>,
+}
+
+/// Unregisters a job however `exec_command` ends, including when its future
+/// is dropped before the command has exited.
+struct RunningJob<'a> {
+    jobs: &'a ExecJobs,
+    job_id: JobId,
+}
+
+impl Drop for RunningJob<'_> {
+    fn drop(&mut self) {
+        self.jobs.cancels.lock().unwrap().remove(&self.job_id);
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ExecCommand {
+    program: String,
+    #[serde(default)]
+    args: Vec<String>,
+    cwd: Option<String>,
+    #[serde(default)]
+    env: HashMap<String, String>,
+    /// Kills the command after this long; no limit when omitted.
+    timeout_ms: Option<u64>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "lowercase")]
+enum OutputStream {
+    Stdout,
+    Stderr,
+}
+
+#[derive(Serialize, Clone)]
+struct ExecOutput {
+    job_id: JobId,
+    stream: OutputStream,
+    data: PtyData,
+}
+
+#[derive(Debug, Default, Serialize)]
+pub struct Captured {
+    pub text: String,
+    /// Output past [`MAX_CAPTURED_BYTES`] was streamed but not kept.
+    pub truncated: bool,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ExecResult {
+    pub job_id: JobId,
+    /// `None` when the process was ended by a signal.
+    pub exit_code: Option<i32>,
+    pub signal: Option<i32>,
+    pub timed_out: bool,
+    pub cancelled: bool,
+    pub stdout: Captured,
+    pub stderr: Captured,
+}
+
+/// A pipe being read. Sending on `stop` ends the read early and hands back
+/// what was captured up to then.
+struct PipeReader {
+    task: JoinHandle<Captured>,
+    stop: oneshot::Sender<()>,
+}
+
+/// Passes one pipe's output to `emit` as it arrives and keeps the start of it.
+fn spawn_reader(
+    mut pipe: impl AsyncRead + Unpin + Send + 'static,
+    mut emit: impl FnMut(PtyData) + Send + 'static,
+) -> PipeReader {
+    let (stop, mut stopped) = oneshot::channel::<()>();
+    let task = async_runtime::spawn(async move {
+        let mut decoder = OutputDecoder::new(OutputEncoding::Utf8);
+        let mut captured = Captured::default();
+        let mut buf = [0u8; READ_BUFFER_SIZE];
+        loop {
+            let n = tokio::select! {
+                read = pipe.read(&mut buf) => match read {
+                    Ok(0) | Err(_) => break,
+                    Ok(n) => n,
+                },
+                _ = &mut stopped => {
+                    captured.truncated = true;
+                    break;
+                }
+            };
+            let data = decoder.decode(&buf[..n]);
+            if let PtyData::Text(text) = &data {
+                if !captured.truncated && captured.text.len() + text.len() <= MAX_CAPTURED_BYTES {
+                    captured.text.push_str(text);
+                } else {
+                    captured.truncated = true;
+                }
+            }
+            emit(data);
+        }
+        captured
+    });
+    PipeReader { task, stop }
+}
+
+/// Streams one of the command's pipes as `exec_output` events.
+fn spawn_output_reader(
+    app: &AppHandle,
+    job_id: &JobId,
+    stream: OutputStream,
+    pipe: impl AsyncRead + Unpin + Send + 'static,
+) -> PipeReader {
+    let app = app.clone();
+    let job_id = job_id.clone();
+    spawn_reader(pipe, move |data| {
+        let _ = app.emit(
+            "exec_output",
+            ExecOutput {
+                job_id: job_id.clone(),
+                stream,
+                data,
+            },
+        );
+    })
+}
+
+/// What a reader captured. Stops waiting for the pipe to close after
+/// [`DRAIN_TIMEOUT`] and keeps the output read so far, marked truncated.
+async fn collect(reader: Option<PipeReader>) -> Captured {
+    let Some(PipeReader { mut task, stop }) = reader else {
+        return Captured::default();
+    };
+    match tokio::time::timeout(DRAIN_TIMEOUT, &mut task).await {
+        Ok(captured) => captured.unwrap_or_default(),
+        Err(_) => {
+            let _ = stop.send(());
+            task.await.unwrap_or_default()
+        }
+    }
+}
+
+/// Ends the command and everything it started; it runs in its own process group.
+fn kill(child: &mut Child) {
+    #[cfg(unix)]
+    if let Some(pid) = child.id() {
+        unsafe { libc::killpg(pid as libc::pid_t, libc::SIGKILL) };
+        return;
+    }
+    let _ = child.start_kill();
+}
+
+#[cfg(unix)]
+fn exit_signal(status: &std::process::ExitStatus) -> Option<i32> {
+    use std::os::unix::process::ExitStatusExt;
+    status.signal()
+}
+
+#[cfg(not(unix))]
+fn exit_signal(_status: &std::process::ExitStatus) -> Option<i32> {
+    None
+}
+
+/// Runs a program outside the terminal, streaming its output as `exec_output`
+/// events tagged with `job_id`, and returns once it has exited.
+#[tauri::command]
+pub async fn exec_command(
+    app: AppHandle,
+    job_id: JobId,
+    command: ExecCommand,
+    state: State<'_, AppState>,
+) -> Result<ExecResult, String> {
+    let (cancel, mut cancelled_rx) = oneshot::channel();
+    {
+        let mut cancels = state.jobs.cancels.lock().unwrap();
+        if cancels.contains_key(&job_id) {
+            return Err(format!("Job {} is already running", job_id));
+        }
+        cancels.insert(job_id.clone(), cancel);
+    }
+    let running = RunningJob {
+        jobs: &state.jobs,
+        job_id: job_id.clone(),
+    };
+
+    let mut cmd = Command::new(&command.program);
+    cmd.args(&command.args)
+        .envs(&command.env)
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .kill_on_drop(true);
+    if let Some(cwd) = &command.cwd {
+        cmd.current_dir(cwd);
+    }
+    #[cfg(unix)]
+    cmd.process_group(0);
+
+    let mut child = cmd
+        .spawn()
+        .map_err(|err| format!("Failed to run {}: {}", command.program, err))?;
+
+    let stdout = child
+        .stdout
+        .take()
+        .map(|pipe| spawn_output_reader(&app, &job_id, OutputStream::Stdout, pipe));
+    let stderr = child
+        .stderr
+        .take()
+        .map(|pipe| spawn_output_reader(&app, &job_id, OutputStream::Stderr, pipe));
+
+    let timeout = async {
+        match command.timeout_ms {
+            Some(ms) => tokio::time::sleep(Duration::from_millis(ms)).await,
+            None => std::future::pending().await,
+        }
+    };
+
+    let (mut timed_out, mut cancelled) = (false, false);
+    let status = tokio::select! {
+        status = child.wait() => status,
+        _ = timeout => {
+            timed_out = true;
+            kill(&mut child);
+            child.wait().await
+        }
+        Ok(()) = &mut cancelled_rx => {
+            cancelled = true;
+            kill(&mut child);
+            child.wait().await
+        }
+    };
+    drop(running);
+    let status = status.map_err(|err| err.to_string())?;
+
+    Ok(ExecResult {
+        job_id,
+        exit_code: status.code(),
+        signal: exit_signal(&status),
+        timed_out,
+        cancelled,
+        stdout: collect(stdout).await,
+        stderr: collect(stderr).await,
+    })
+}
+
+/// Kills a running `exec_command` job; the command then returns with `cancelled` set.
+#[tauri::command]
+pub async fn cancel_exec(job_id: JobId, state: State<'_, AppState>) -> Result<(), String> {
+    let cancel = state
+        .jobs
+        .cancels
+        .lock()
+        .unwrap()
+        .remove(&job_id)
+        .ok_or_else(|| format!("Unknown job: {}", job_id))?;
+    let _ = cancel.send(());
+    Ok(())
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn keeps_output_when_a_background_child_holds_the_pipe() {
+        async_runtime::block_on(async {
+            let mut child = Command::new("sh")
+                .args(["-c", "sleep 100 & echo hi"])
+                .stdout(Stdio::piped())
+                .process_group(0)
+                .spawn()
+                .unwrap();
+            let pgid = child.id().unwrap() as libc::pid_t;
+            let reader = spawn_reader(child.stdout.take().unwrap(), |_| {});
+            child.wait().await.unwrap();
+
+            let captured = collect(Some(reader)).await;
+            unsafe { libc::killpg(pgid, libc::SIGKILL) };
+            assert_eq!(captured.text, "hi\n");
+            assert!(captured.truncated);
+        });
+    }
+
+    #[test]
+    fn captures_everything_once_the_pipe_closes() {
+        async_runtime::block_on(async {
+            let mut child = Command::new("sh")
+                .args(["-c", "echo out"])
+                .stdout(Stdio::piped())
+                .spawn()
+                .unwrap();
+            let reader = spawn_reader(child.stdout.take().unwrap(), |_| {});
+            child.wait().await.unwrap();
+
+            let captured = collect(Some(reader)).await;
+            assert_eq!(captured.text, "out\n");
+            assert!(!captured.truncated);
+        });
+    }
+}
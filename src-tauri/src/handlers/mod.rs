@@ -1,5 +1,6 @@
 pub mod ai;
 pub mod config;
 pub mod events;
+pub mod exec;
 pub mod file_system;
 pub mod term;
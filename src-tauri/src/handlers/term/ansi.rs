@@ -0,0 +1,166 @@
+use vte::{Params, Parser, Perform};
+
+use super::screen::{flatten_params, Attrs};
+
+/// Columns a rendered line can reach; cursor moves stop here and characters
+/// past it are dropped, so `ESC[65535C` repeated cannot blow up a line.
+const MAX_LINE_WIDTH: usize = 64 * 1024;
+
+/// Style and OSC 8 hyperlink in effect, carried from one line to the next.
+#[derive(Debug, Clone, Default)]
+pub struct LineState {
+    pub attrs: Attrs,
+    pub hyperlink: Option<String>,
+}
+
+/// Characters `start..end` of a line are the text of an OSC 8 hyperlink to `uri`.
+#[derive(Debug, Clone)]
+pub struct Hyperlink {
+    pub start: usize,
+    pub end: usize,
+    pub uri: String,
+}
+
+pub struct RenderedLine {
+    pub cells: Vec<(char, Attrs)>,
+    pub hyperlinks: Vec<Hyperlink>,
+}
+
+/// Replays one line of output onto a row of styled characters: escapes are
+/// dropped, `\r` and backspace overwrite, so progress bars read the way they looked.
+#[derive(Default)]
+struct LineRenderer {
+    cells: Vec<(char, Attrs)>,
+    hyperlinks: Vec<Hyperlink>,
+    cursor: usize,
+    state: LineState,
+}
+
+impl LineRenderer {
+    fn put(&mut self, c: char) {
+        if self.cursor >= MAX_LINE_WIDTH {
+            return;
+        }
+        let attrs = self.state.attrs;
+        if self.cursor < self.cells.len() {
+            self.cells[self.cursor] = (c, attrs);
+        } else {
+            self.cells.resize(self.cursor, (' ', Attrs::default()));
+            self.cells.push((c, attrs));
+        }
+
+        if let Some(uri) = &self.state.hyperlink {
+            match self.hyperlinks.last_mut() {
+                Some(last) if last.end == self.cursor && &last.uri == uri => last.end += 1,
+                _ => self.hyperlinks.push(Hyperlink {
+                    start: self.cursor,
+                    end: self.cursor + 1,
+                    uri: uri.clone(),
+                }),
+            }
+        }
+        self.cursor += 1;
+    }
+}
+
+impl Perform for LineRenderer {
+    fn print(&mut self, c: char) {
+        self.put(c);
+    }
+
+    fn execute(&mut self, byte: u8) {
+        match byte {
+            b'\r' => self.cursor = 0,
+            0x08 => self.cursor = self.cursor.saturating_sub(1),
+            b'\t' => self.put('\t'),
+            _ => {}
+        }
+    }
+
+    fn csi_dispatch(&mut self, params: &Params, intermediates: &[u8], ignore: bool, action: char) {
+        if ignore || !intermediates.is_empty() {
+            return;
+        }
+        let params = flatten_params(params);
+        let count = params.first().copied().filter(|&n| n > 0).unwrap_or(1) as usize;
+
+        match action {
+            'C' => self.cursor = (self.cursor + count).min(MAX_LINE_WIDTH),
+            'D' => self.cursor = self.cursor.saturating_sub(count),
+            'G' => self.cursor = (count - 1).min(MAX_LINE_WIDTH),
+            'K' => match params.first().copied().unwrap_or(0) {
+                0 => self.cells.truncate(self.cursor),
+                1 => {
+                    let end = (self.cursor + 1).min(self.cells.len());
+                    self.cells[..end].fill((' ', Attrs::default()));
+                }
+                2 => self.cells.clear(),
+                _ => {}
+            },
+            'm' => self.state.attrs.apply_sgr(&params),
+            _ => {}
+        }
+    }
+
+    fn osc_dispatch(&mut self, params: &[&[u8]], _bell_terminated: bool) {
+        // OSC 8 ; params ; uri — an empty uri closes the link. The uri may contain `;`.
+        if let [b"8", _, uri @ ..] = params {
+            let uri = uri
+                .iter()
+                .map(|part| String::from_utf8_lossy(part))
+                .collect::<Vec<_>>()
+                .join(";");
+            self.state.hyperlink = (!uri.is_empty()).then_some(uri);
+        }
+    }
+}
+
+/// Characters of a single line of raw output with the style each was drawn in.
+/// `state` is what was in effect before the line and is updated to what is
+/// after it, since SGR and OSC 8 state carry over from line to line.
+pub fn render_line(line: &[u8], state: &mut LineState) -> RenderedLine {
+    let mut parser = Parser::new();
+    let mut renderer = LineRenderer {
+        state: std::mem::take(state),
+        ..Default::default()
+    };
+    parser.advance(&mut renderer, line);
+    *state = renderer.state;
+
+    RenderedLine {
+        cells: renderer.cells,
+        hyperlinks: renderer.hyperlinks,
+    }
+}
+
+/// Plain text of a single line of raw output, without escapes or the trailing newline.
+pub fn strip_line(line: &[u8]) -> String {
+    render_line(line, &mut LineState::default())
+        .cells
+        .into_iter()
+        .map(|(c, _)| c)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn cursor_moves_stop_at_the_maximum_width() {
+        let mut line = b"a".to_vec();
+        for _ in 0..100 {
+            line.extend_from_slice(b"\x1b[65535C");
+        }
+        line.extend_from_slice(b"b\n");
+
+        let rendered = render_line(&line, &mut LineState::default());
+        assert_eq!(rendered.cells.len(), 1);
+        assert_eq!(rendered.cells[0].0, 'a');
+    }
+
+    #[test]
+    fn text_after_a_cursor_move_is_padded() {
+        assert_eq!(strip_line(b"ab\x1b[3Cc\x1b[1Gd"), "db   c");
+    }
+}
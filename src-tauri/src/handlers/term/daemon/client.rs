@@ -0,0 +1,313 @@
+use portable_pty::{Child, ChildKiller, CommandBuilder, ExitStatus, MasterPty, PtySize};
+use std::{
+    io::{self, Read},
+    os::unix::{net::UnixStream, process::CommandExt},
+    process::{Command, Stdio},
+    sync::{
+        atomic::{AtomicI32, Ordering},
+        Arc, Mutex, Weak,
+    },
+    thread,
+    time::Duration,
+};
+
+use super::{
+    read_message, socket_path, write_message, DaemonSession, Request, Response, DAEMON_BINARY,
+};
+use crate::handlers::term::{
+    decoder::OutputEncoding, scrollback::ScrollbackLimit, session::SessionId,
+};
+
+const STARTUP_POLL_INTERVAL: Duration = Duration::from_millis(50);
+const STARTUP_ATTEMPTS: usize = 40;
+/// How long a request may go unanswered before the daemon is taken to be hung.
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// Why the daemon failed to start, kept so later shells go straight to local PTYs.
+static UNAVAILABLE: Mutex<Option<String>> = Mutex::new(None);
+
+/// A daemon-owned session as seen from the app: the same parts a local PTY hands out.
+pub struct DaemonPty {
+    pub master: Box<dyn MasterPty + Send>,
+    pub child: Box<dyn Child + Send + Sync>,
+}
+
+/// A hung daemon must not hang the app, e.g. while it restores sessions at startup.
+fn connect() -> io::Result<UnixStream> {
+    let stream = UnixStream::connect(socket_path()?)?;
+    stream.set_read_timeout(Some(REQUEST_TIMEOUT))?;
+    stream.set_write_timeout(Some(REQUEST_TIMEOUT))?;
+    Ok(stream)
+}
+
+fn read_response(stream: &mut UnixStream) -> io::Result<Response> {
+    let response: Response = read_message(stream)?;
+    match response.error {
+        Some(err) => Err(io::Error::other(err)),
+        None => Ok(response),
+    }
+}
+
+fn call(stream: &mut UnixStream, request: &Request) -> io::Result<Response> {
+    write_message(stream, request)?;
+    read_response(stream)
+}
+
+fn request(request: &Request) -> io::Result<Response> {
+    call(&mut connect()?, request)
+}
+
+/// Sends a request whose answers may take as long as the session runs.
+fn watch(request: &Request) -> io::Result<UnixStream> {
+    let mut stream = connect()?;
+    write_message(&mut stream, request)?;
+    stream.set_read_timeout(None)?;
+    stream.set_write_timeout(None)?;
+    Ok(stream)
+}
+
+/// Starts the daemon unless one is listening already.
+fn ensure_running() -> io::Result<()> {
+    if connect().is_ok() {
+        return Ok(());
+    }
+
+    // Bundled as a sidecar, which Tauri places next to the app's executable.
+    let binary = std::env::current_exe()?.with_file_name(DAEMON_BINARY);
+    let mut command = Command::new(&binary);
+    command
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null());
+    // Its own session, so closing the window (and its terminal, if any) does not hang it up.
+    unsafe {
+        command.pre_exec(|| {
+            if libc::setsid() < 0 {
+                return Err(io::Error::last_os_error());
+            }
+            Ok(())
+        });
+    }
+    let mut daemon = command.spawn().map_err(|err| {
+        io::Error::new(
+            err.kind(),
+            format!("Cannot start {}: {}", binary.display(), err),
+        )
+    })?;
+    thread::spawn(move || daemon.wait());
+
+    for _ in 0..STARTUP_ATTEMPTS {
+        thread::sleep(STARTUP_POLL_INTERVAL);
+        if connect().is_ok() {
+            return Ok(());
+        }
+    }
+    Err(io::Error::new(
+        io::ErrorKind::TimedOut,
+        "Session daemon did not start",
+    ))
+}
+
+/// Spawns `cmd` in the daemon under the app's session ID.
+pub fn spawn(
+    id: SessionId,
+    profile: &str,
+    encoding: OutputEncoding,
+    scrollback: ScrollbackLimit,
+    cmd: &CommandBuilder,
+    size: PtySize,
+) -> io::Result<DaemonPty> {
+    if let Some(reason) = UNAVAILABLE.lock().unwrap().clone() {
+        return Err(io::Error::other(reason));
+    }
+    if let Err(err) = ensure_running() {
+        *UNAVAILABLE.lock().unwrap() = Some(err.to_string());
+        return Err(err);
+    }
+
+    let mut stream = connect()?;
+    let response = call(
+        &mut stream,
+        &Request::Spawn {
+            id,
+            profile: profile.to_string(),
+            encoding,
+            scrollback,
+            argv: cmd
+                .get_argv()
+                .iter()
+                .map(|arg| arg.to_string_lossy().into_owned())
+                .collect(),
+            cwd: cmd.get_cwd().map(|cwd| cwd.to_string_lossy().into_owned()),
+            env: cmd
+                .iter_full_env_as_str()
+                .map(|(key, value)| (key.to_string(), value.to_string()))
+                .collect(),
+            size: size.into(),
+        },
+    )?;
+    // From here on the stream carries output and input, either of which may
+    // pause for any length of time.
+    stream.set_read_timeout(None)?;
+    stream.set_write_timeout(None)?;
+    Ok(open(id, stream, response.pid, size))
+}
+
+/// Sessions a previous run left in the daemon; empty when no daemon is running.
+pub fn list() -> Vec<DaemonSession> {
+    request(&Request::List)
+        .map(|response| response.sessions)
+        .unwrap_or_default()
+}
+
+/// Takes over a daemon session's stream. Returns the output the daemon kept
+/// for it; live output follows on the session's reader.
+pub fn attach(session: &DaemonSession) -> io::Result<(DaemonPty, Vec<u8>)> {
+    let mut stream = connect()?;
+    let response = call(&mut stream, &Request::Attach { id: session.id })?;
+
+    let mut history = vec![0u8; response.history.unwrap_or(0) as usize];
+    stream.read_exact(&mut history)?;
+    stream.set_read_timeout(None)?;
+    stream.set_write_timeout(None)?;
+    Ok((
+        open(session.id, stream, session.pid, session.size.into()),
+        history,
+    ))
+}
+
+fn open(id: SessionId, stream: UnixStream, pid: Option<u32>, size: PtySize) -> DaemonPty {
+    let foreground = Arc::new(AtomicI32::new(0));
+    watch_foreground(id, Arc::downgrade(&foreground));
+    DaemonPty {
+        master: Box::new(DaemonMaster {
+            id,
+            stream,
+            size: Mutex::new(size),
+            foreground,
+        }),
+        child: Box::new(DaemonChild {
+            killer: DaemonKiller { id },
+            pid,
+        }),
+    }
+}
+
+/// Keeps `foreground` at the process group the daemon last reported, so the
+/// status poller reads it without a round trip. Ends with the session.
+fn watch_foreground(id: SessionId, foreground: Weak<AtomicI32>) {
+    thread::spawn(move || {
+        let Ok(mut stream) = watch(&Request::Foreground { id }) else {
+            return;
+        };
+        while let Ok(response) = read_response(&mut stream) {
+            let Some(foreground) = foreground.upgrade() else {
+                break;
+            };
+            foreground.store(response.pgid.unwrap_or(0), Ordering::Relaxed);
+        }
+    });
+}
+
+/// The app's end of a daemon session: output and input travel over `stream`,
+/// everything else is a separate request.
+struct DaemonMaster {
+    id: SessionId,
+    stream: UnixStream,
+    size: Mutex<PtySize>,
+    /// Foreground process group, or 0 while unknown.
+    foreground: Arc<AtomicI32>,
+}
+
+impl MasterPty for DaemonMaster {
+    fn resize(&self, size: PtySize) -> anyhow::Result<()> {
+        request(&Request::Resize {
+            id: self.id,
+            size: size.into(),
+        })?;
+        *self.size.lock().unwrap() = size;
+        Ok(())
+    }
+
+    fn get_size(&self) -> anyhow::Result<PtySize> {
+        Ok(*self.size.lock().unwrap())
+    }
+
+    fn try_clone_reader(&self) -> anyhow::Result<Box<dyn Read + Send>> {
+        Ok(Box::new(self.stream.try_clone()?))
+    }
+
+    fn take_writer(&self) -> anyhow::Result<Box<dyn io::Write + Send>> {
+        Ok(Box::new(self.stream.try_clone()?))
+    }
+
+    fn process_group_leader(&self) -> Option<libc::pid_t> {
+        match self.foreground.load(Ordering::Relaxed) {
+            0 => None,
+            pgid => Some(pgid),
+        }
+    }
+
+    /// The terminal lives in the daemon; there is no descriptor on this side.
+    fn as_raw_fd(&self) -> Option<std::os::unix::io::RawFd> {
+        None
+    }
+}
+
+#[derive(Debug, Clone)]
+struct DaemonKiller {
+    id: SessionId,
+}
+
+impl ChildKiller for DaemonKiller {
+    fn kill(&mut self) -> io::Result<()> {
+        request(&Request::Kill { id: self.id }).map(|_| ())
+    }
+
+    fn clone_killer(&self) -> Box<dyn ChildKiller + Send + Sync> {
+        Box::new(self.clone())
+    }
+}
+
+#[derive(Debug)]
+struct DaemonChild {
+    killer: DaemonKiller,
+    pid: Option<u32>,
+}
+
+impl ChildKiller for DaemonChild {
+    fn kill(&mut self) -> io::Result<()> {
+        self.killer.kill()
+    }
+
+    fn clone_killer(&self) -> Box<dyn ChildKiller + Send + Sync> {
+        self.killer.clone_killer()
+    }
+}
+
+impl Child for DaemonChild {
+    fn try_wait(&mut self) -> io::Result<Option<ExitStatus>> {
+        let exited = request(&Request::List)?
+            .sessions
+            .iter()
+            .any(|session| session.id == self.killer.id && session.exited);
+        if exited {
+            self.wait().map(Some)
+        } else {
+            Ok(None)
+        }
+    }
+
+    /// Blocks until the daemon reports the exit, after which it forgets the session.
+    fn wait(&mut self) -> io::Result<ExitStatus> {
+        let response = read_response(&mut watch(&Request::Wait { id: self.killer.id })?)?;
+        Ok(match response.signal {
+            Some(signal) => ExitStatus::with_signal(&signal),
+            None => ExitStatus::with_exit_code(response.exit_code.unwrap_or(1)),
+        })
+    }
+
+    fn process_id(&self) -> Option<u32> {
+        self.pid
+    }
+}
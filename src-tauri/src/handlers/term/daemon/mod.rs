@@ -0,0 +1,177 @@
+//! Session daemon: a separate process that owns the PTYs so shells outlive the
+//! UI. Every connection starts with one JSON request line and one JSON response
+//! line. `spawn` and `attach` connections then turn into the session's raw
+//! byte stream (output from the daemon, input to it); all other requests are
+//! one-shot.
+
+pub mod client;
+pub mod server;
+
+use portable_pty::PtySize;
+use serde::{Deserialize, Serialize};
+use std::{
+    fs,
+    io::{self, Read, Write},
+    os::unix::fs::{DirBuilderExt, MetadataExt},
+    path::PathBuf,
+};
+
+use super::{decoder::OutputEncoding, scrollback::ScrollbackLimit, session::SessionId};
+
+pub const DAEMON_BINARY: &str = "sci-fi-ui-daemon";
+const MAX_MESSAGE_SIZE: usize = 1024 * 1024;
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct WireSize {
+    pub rows: u16,
+    pub cols: u16,
+    pub pixel_width: u16,
+    pub pixel_height: u16,
+}
+
+impl From<PtySize> for WireSize {
+    fn from(size: PtySize) -> Self {
+        WireSize {
+            rows: size.rows,
+            cols: size.cols,
+            pixel_width: size.pixel_width,
+            pixel_height: size.pixel_height,
+        }
+    }
+}
+
+impl From<WireSize> for PtySize {
+    fn from(size: WireSize) -> Self {
+        PtySize {
+            rows: size.rows,
+            cols: size.cols,
+            pixel_width: size.pixel_width,
+            pixel_height: size.pixel_height,
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum Request {
+    /// Starts a shell under the app's session ID and streams it.
+    Spawn {
+        id: SessionId,
+        /// Kept for a restarted UI, which restores the session with the same settings.
+        profile: String,
+        encoding: OutputEncoding,
+        scrollback: ScrollbackLimit,
+        argv: Vec<String>,
+        cwd: Option<String>,
+        env: Vec<(String, String)>,
+        size: WireSize,
+    },
+    /// Streams an existing session, starting with the output the daemon kept.
+    Attach {
+        id: SessionId,
+    },
+    Resize {
+        id: SessionId,
+        size: WireSize,
+    },
+    /// Answers with the foreground process group, then again whenever it
+    /// changes, until the session's child exits.
+    Foreground {
+        id: SessionId,
+    },
+    Kill {
+        id: SessionId,
+    },
+    /// Answers once the session's child has exited; the daemon then forgets it.
+    Wait {
+        id: SessionId,
+    },
+    List,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DaemonSession {
+    pub id: SessionId,
+    pub profile: String,
+    pub encoding: OutputEncoding,
+    pub scrollback: ScrollbackLimit,
+    pub pid: Option<u32>,
+    pub size: WireSize,
+    pub exited: bool,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct Response {
+    pub error: Option<String>,
+    pub pid: Option<u32>,
+    pub pgid: Option<i32>,
+    pub exit_code: Option<u32>,
+    pub signal: Option<String>,
+    /// Bytes of kept output that follow an `attach` response, ahead of live output.
+    pub history: Option<u64>,
+    #[serde(default)]
+    pub sessions: Vec<DaemonSession>,
+}
+
+impl Response {
+    pub fn error(message: impl Into<String>) -> Self {
+        Response {
+            error: Some(message.into()),
+            ..Default::default()
+        }
+    }
+}
+
+/// `$XDG_RUNTIME_DIR/sci-fi-ui/daemon.sock`, or a per-user directory under the temp dir.
+pub fn socket_path() -> io::Result<PathBuf> {
+    let dir = match std::env::var_os("XDG_RUNTIME_DIR") {
+        Some(runtime) => PathBuf::from(runtime).join("sci-fi-ui"),
+        None => std::env::temp_dir().join(format!("sci-fi-ui-{}", unsafe { libc::getuid() })),
+    };
+    // Only the owner may reach the shells.
+    fs::DirBuilder::new()
+        .recursive(true)
+        .mode(0o700)
+        .create(&dir)?;
+    // The directory may have been there already, e.g. made in /tmp by another
+    // user to catch our environment and keystrokes; only trust our own.
+    let metadata = fs::symlink_metadata(&dir)?;
+    if !metadata.is_dir()
+        || metadata.uid() != unsafe { libc::getuid() }
+        || metadata.mode() & 0o777 != 0o700
+    {
+        return Err(io::Error::new(
+            io::ErrorKind::PermissionDenied,
+            format!("Refusing to use {}: not a private directory", dir.display()),
+        ));
+    }
+    Ok(dir.join("daemon.sock"))
+}
+
+pub fn write_message<T: Serialize>(writer: &mut impl Write, message: &T) -> io::Result<()> {
+    let mut line = serde_json::to_vec(message)?;
+    line.push(b'\n');
+    writer.write_all(&line)?;
+    writer.flush()
+}
+
+/// Reads one message a byte at a time: nothing past the newline may be consumed,
+/// since on `spawn` and `attach` connections the session's bytes follow directly.
+pub fn read_message<T: for<'de> Deserialize<'de>>(reader: &mut impl Read) -> io::Result<T> {
+    let mut line = Vec::new();
+    let mut byte = [0u8];
+    loop {
+        reader.read_exact(&mut byte)?;
+        if byte[0] == b'\n' {
+            break;
+        }
+        if line.len() >= MAX_MESSAGE_SIZE {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "Daemon message too long",
+            ));
+        }
+        line.push(byte[0]);
+    }
+    serde_json::from_slice(&line).map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))
+}
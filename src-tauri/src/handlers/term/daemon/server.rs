@@ -0,0 +1,444 @@
+use portable_pty::{
+    native_pty_system, ChildKiller, CommandBuilder, ExitStatus, MasterPty, PtySize,
+};
+use std::{
+    collections::{HashMap, VecDeque},
+    fs,
+    io::{self, Read, Write},
+    net::Shutdown,
+    os::unix::net::{UnixListener, UnixStream},
+    path::{Path, PathBuf},
+    sync::{
+        mpsc::{self, Receiver, SyncSender},
+        Arc, Condvar, Mutex,
+    },
+    thread,
+    time::Duration,
+};
+
+use super::{read_message, socket_path, write_message, DaemonSession, Request, Response};
+use crate::handlers::term::{
+    decoder::OutputEncoding,
+    scrollback::ScrollbackLimit,
+    session::{exit_signal, SessionId},
+};
+
+/// Output kept per session for the next UI that attaches.
+const MAX_HISTORY_BYTES: usize = 4 * 1024 * 1024;
+const READ_BUFFER_SIZE: usize = 8 * 1024;
+/// Output chunks queued for a UI before the shell has to wait for it to catch up.
+const CLIENT_QUEUE_CHUNKS: usize = 64;
+/// How often a `foreground` watch checks the terminal's process group.
+const FOREGROUND_POLL_INTERVAL: Duration = Duration::from_millis(250);
+
+type Sessions = Arc<Mutex<HashMap<SessionId, Arc<Session>>>>;
+
+/// Output kept so far and the queue of the UI it is forwarded to, locked
+/// together so an attaching client sees every byte exactly once.
+#[derive(Default)]
+struct Stream {
+    history: VecDeque<u8>,
+    client: Option<SyncSender<Vec<u8>>>,
+    /// Counts attaches, so a client's input thread can tell whether it is still the current one.
+    attachment: u64,
+    /// The PTY reported EOF; clients get the history and are hung up on.
+    closed: bool,
+}
+
+struct Session {
+    profile: String,
+    encoding: OutputEncoding,
+    scrollback: ScrollbackLimit,
+    pid: Option<u32>,
+    master: Mutex<Box<dyn MasterPty + Send>>,
+    writer: Mutex<Box<dyn Write + Send>>,
+    killer: Mutex<Box<dyn ChildKiller + Send + Sync>>,
+    size: Mutex<PtySize>,
+    stream: Mutex<Stream>,
+    status: Mutex<Option<ExitStatus>>,
+    exited: Condvar,
+}
+
+impl Session {
+    /// Makes `client` the session's only UI connection: it gets the kept
+    /// output first, then live output, and its bytes become the shell's input.
+    /// Refused while another UI is attached; a UI that went away has been
+    /// detached by its input thread.
+    fn attach(self: &Arc<Self>, connection: &UnixStream) -> io::Result<()> {
+        let client = connection.try_clone()?;
+        let input = connection.try_clone()?;
+        let mut stream = self.stream.lock().unwrap();
+        if stream.client.is_some() {
+            return Err(io::Error::new(
+                io::ErrorKind::AddrInUse,
+                "The session is attached to another window",
+            ));
+        }
+        let mut kept = Vec::with_capacity(stream.history.len() + 128);
+        write_message(
+            &mut kept,
+            &Response {
+                pid: self.pid,
+                history: Some(stream.history.len() as u64),
+                ..Default::default()
+            },
+        )?;
+        kept.extend(&stream.history);
+        let (queue, chunks) = mpsc::sync_channel(CLIENT_QUEUE_CHUNKS);
+        let _ = queue.send(kept);
+        thread::spawn(move || forward_to_client(client, chunks));
+
+        // Dropping the queue hangs up on the client once it has the history.
+        if stream.closed {
+            return Ok(());
+        }
+        stream.client = Some(queue);
+        stream.attachment += 1;
+        let attachment = stream.attachment;
+        drop(stream);
+
+        let session = self.clone();
+        thread::spawn(move || session.forward_input(input, attachment));
+        Ok(())
+    }
+
+    fn forward_input(&self, mut input: UnixStream, attachment: u64) {
+        let mut buf = [0u8; READ_BUFFER_SIZE];
+        loop {
+            match input.read(&mut buf) {
+                Ok(0) | Err(_) => break,
+                Ok(n) => {
+                    let mut writer = self.writer.lock().unwrap();
+                    if writer
+                        .write_all(&buf[..n])
+                        .and_then(|_| writer.flush())
+                        .is_err()
+                    {
+                        break;
+                    }
+                }
+            }
+        }
+
+        // The UI hung up, or crashed; the next one may attach.
+        let mut stream = self.stream.lock().unwrap();
+        if stream.attachment == attachment {
+            stream.client = None;
+        }
+    }
+
+    fn forward_output(&self, mut reader: Box<dyn Read + Send>) {
+        let mut buf = [0u8; READ_BUFFER_SIZE];
+        loop {
+            let n = match reader.read(&mut buf) {
+                // EOF, or EIO on Linux once the child side has gone away.
+                Ok(0) | Err(_) => break,
+                Ok(n) => n,
+            };
+
+            let client = {
+                let mut stream = self.stream.lock().unwrap();
+                stream.history.extend(&buf[..n]);
+                let excess = stream.history.len().saturating_sub(MAX_HISTORY_BYTES);
+                stream.history.drain(..excess);
+                let attachment = stream.attachment;
+                stream.client.clone().map(|queue| (attachment, queue))
+            };
+            // Queued outside the lock: a UI that is slow to read holds up this
+            // shell's output, never the daemon's other requests.
+            if let Some((attachment, queue)) = client {
+                if queue.send(buf[..n].to_vec()).is_err() {
+                    // A UI that went away keeps nothing alive; the output waits in the history.
+                    let mut stream = self.stream.lock().unwrap();
+                    if stream.attachment == attachment {
+                        stream.client = None;
+                    }
+                }
+            }
+        }
+
+        let mut stream = self.stream.lock().unwrap();
+        stream.closed = true;
+        // The client is hung up on once it has the rest of the output.
+        stream.client = None;
+    }
+
+    /// Reports the foreground process group now and after every change, until
+    /// the child exits or the UI hangs up.
+    fn watch_foreground(&self, connection: &mut UnixStream) -> Result<(), String> {
+        let mut reported = None;
+        loop {
+            let pgid = self.master.lock().unwrap().process_group_leader();
+            if reported != Some(pgid) {
+                reply(
+                    connection,
+                    Response {
+                        pgid,
+                        ..Default::default()
+                    },
+                )?;
+                reported = Some(pgid);
+            }
+
+            let status = self.status.lock().unwrap();
+            let (status, _) = self
+                .exited
+                .wait_timeout(status, FOREGROUND_POLL_INTERVAL)
+                .unwrap();
+            if status.is_some() {
+                return Ok(());
+            }
+        }
+    }
+
+    fn describe(&self, id: SessionId) -> DaemonSession {
+        DaemonSession {
+            id,
+            profile: self.profile.clone(),
+            encoding: self.encoding,
+            scrollback: self.scrollback,
+            pid: self.pid,
+            size: (*self.size.lock().unwrap()).into(),
+            exited: self.status.lock().unwrap().is_some(),
+        }
+    }
+}
+
+/// Writes a UI's queued output to it, and hangs up once the queue is dropped
+/// or the UI stops reading.
+fn forward_to_client(mut client: UnixStream, chunks: Receiver<Vec<u8>>) {
+    for chunk in chunks {
+        if client.write_all(&chunk).is_err() {
+            break;
+        }
+    }
+    let _ = client.shutdown(Shutdown::Both);
+}
+
+/// Runs the daemon until its last session has exited and been collected.
+/// Exits right away if another daemon already owns the socket.
+pub fn run() {
+    let path = match socket_path() {
+        Ok(path) => path,
+        Err(err) => {
+            eprintln!("Failed to prepare the daemon socket directory: {}", err);
+            std::process::exit(1);
+        }
+    };
+    if UnixStream::connect(&path).is_ok() {
+        return;
+    }
+    // Left behind by a daemon that crashed.
+    let _ = fs::remove_file(&path);
+
+    let listener = match UnixListener::bind(&path) {
+        Ok(listener) => listener,
+        Err(err) => {
+            eprintln!("Failed to listen on {}: {}", path.display(), err);
+            std::process::exit(1);
+        }
+    };
+
+    let sessions: Sessions = Arc::new(Mutex::new(HashMap::new()));
+    for connection in listener.incoming() {
+        let Ok(connection) = connection else {
+            continue;
+        };
+        let sessions = sessions.clone();
+        let path = path.clone();
+        thread::spawn(move || handle(connection, sessions, path));
+    }
+}
+
+fn handle(mut connection: UnixStream, sessions: Sessions, path: PathBuf) {
+    let request = match read_message::<Request>(&mut connection) {
+        Ok(request) => request,
+        Err(err) => {
+            let _ = write_message(&mut connection, &Response::error(err.to_string()));
+            return;
+        }
+    };
+
+    let result = match request {
+        Request::Spawn {
+            id,
+            profile,
+            encoding,
+            scrollback,
+            argv,
+            cwd,
+            env,
+            size,
+        } => {
+            let mut cmd = CommandBuilder::from_argv(argv.into_iter().map(Into::into).collect());
+            // The app sends its whole environment, so the shell starts as if the app ran it.
+            cmd.env_clear();
+            for (key, value) in env {
+                cmd.env(key, value);
+            }
+            if let Some(cwd) = cwd {
+                cmd.cwd(cwd);
+            }
+            spawn(
+                &sessions,
+                id,
+                profile,
+                encoding,
+                scrollback,
+                cmd,
+                size.into(),
+            )
+            .and_then(|session| session.attach(&connection).map_err(|err| err.to_string()))
+        }
+        Request::Attach { id } => get(&sessions, id)
+            .and_then(|session| session.attach(&connection).map_err(|err| err.to_string())),
+        Request::Resize { id, size } => get(&sessions, id).and_then(|session| {
+            let size = PtySize::from(size);
+            session
+                .master
+                .lock()
+                .unwrap()
+                .resize(size)
+                .map_err(|err| err.to_string())?;
+            *session.size.lock().unwrap() = size;
+            reply(&mut connection, Response::default())
+        }),
+        Request::Foreground { id } => {
+            get(&sessions, id).and_then(|session| session.watch_foreground(&mut connection))
+        }
+        Request::Kill { id } => get(&sessions, id).and_then(|session| {
+            session
+                .killer
+                .lock()
+                .unwrap()
+                .kill()
+                .map_err(|err| err.to_string())?;
+            reply(&mut connection, Response::default())
+        }),
+        Request::Wait { id } => get(&sessions, id).and_then(|session| {
+            let status = session.status.lock().unwrap();
+            let status = session
+                .exited
+                .wait_while(status, |status| status.is_none())
+                .unwrap()
+                .clone()
+                .unwrap();
+            reply(
+                &mut connection,
+                Response {
+                    exit_code: Some(status.exit_code()),
+                    signal: exit_signal(&status),
+                    ..Default::default()
+                },
+            )?;
+            // Only forgotten once a UI has heard about the exit; until then a
+            // restarted UI can still show what the shell printed last.
+            collect(&sessions, id, &path);
+            Ok(())
+        }),
+        Request::List => {
+            let sessions: Vec<DaemonSession> = sessions
+                .lock()
+                .unwrap()
+                .iter()
+                .map(|(id, session)| session.describe(*id))
+                .collect();
+            reply(
+                &mut connection,
+                Response {
+                    sessions,
+                    ..Default::default()
+                },
+            )
+        }
+    };
+
+    if let Err(err) = result {
+        let _ = write_message(&mut connection, &Response::error(err));
+    }
+}
+
+fn reply(connection: &mut UnixStream, response: Response) -> Result<(), String> {
+    write_message(connection, &response).map_err(|err| err.to_string())
+}
+
+fn get(sessions: &Sessions, id: SessionId) -> Result<Arc<Session>, String> {
+    sessions
+        .lock()
+        .unwrap()
+        .get(&id)
+        .cloned()
+        .ok_or_else(|| format!("Unknown terminal session: {}", id))
+}
+
+fn spawn(
+    sessions: &Sessions,
+    id: SessionId,
+    profile: String,
+    encoding: OutputEncoding,
+    scrollback: ScrollbackLimit,
+    cmd: CommandBuilder,
+    size: PtySize,
+) -> Result<Arc<Session>, String> {
+    let mut sessions = sessions.lock().unwrap();
+    if sessions.contains_key(&id) {
+        return Err(format!("Terminal session {} already exists", id));
+    }
+
+    let pty_pair = native_pty_system()
+        .openpty(size)
+        .map_err(|err| err.to_string())?;
+    let mut child = pty_pair
+        .slave
+        .spawn_command(cmd)
+        .map_err(|err| err.to_string())?;
+    drop(pty_pair.slave);
+
+    let reader = pty_pair
+        .master
+        .try_clone_reader()
+        .map_err(|err| err.to_string())?;
+    let writer = pty_pair
+        .master
+        .take_writer()
+        .map_err(|err| err.to_string())?;
+
+    let session = Arc::new(Session {
+        profile,
+        encoding,
+        scrollback,
+        pid: child.process_id(),
+        master: Mutex::new(pty_pair.master),
+        writer: Mutex::new(writer),
+        killer: Mutex::new(child.clone_killer()),
+        size: Mutex::new(size),
+        stream: Mutex::new(Stream::default()),
+        status: Mutex::new(None),
+        exited: Condvar::new(),
+    });
+    sessions.insert(id, session.clone());
+
+    let output = session.clone();
+    thread::spawn(move || output.forward_output(reader));
+    let waiter = session.clone();
+    thread::spawn(move || {
+        let status = child
+            .wait()
+            .unwrap_or_else(|_| ExitStatus::with_exit_code(1));
+        *waiter.status.lock().unwrap() = Some(status);
+        waiter.exited.notify_all();
+    });
+
+    Ok(session)
+}
+
+/// Drops an exited session; the daemon goes away with its last one.
+fn collect(sessions: &Sessions, id: SessionId, path: &Path) {
+    let mut sessions = sessions.lock().unwrap();
+    sessions.remove(&id);
+    if sessions.is_empty() {
+        // Removed while the lock is held, so no new spawn can slip in.
+        let _ = fs::remove_file(path);
+        std::process::exit(0);
+    }
+}
@@ -0,0 +1,117 @@
+use serde::{Deserialize, Serialize};
+
+/// How PTY output is handed to the webview.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum OutputEncoding {
+    /// Text decoded as UTF-8; sequences split across reads are stitched back together.
+    #[default]
+    Utf8,
+    /// Untouched bytes, for terminal emulators that do their own decoding.
+    Raw,
+}
+
+#[derive(Serialize, Clone)]
+#[serde(untagged)]
+pub enum PtyData {
+    Text(String),
+    Raw(Vec<u8>),
+}
+
+/// UTF-8 decoder that carries an incomplete trailing sequence over to the next chunk.
+#[derive(Default)]
+pub struct Utf8Decoder {
+    pending: Vec<u8>,
+}
+
+impl Utf8Decoder {
+    pub fn decode(&mut self, chunk: &[u8]) -> String {
+        let mut input = std::mem::take(&mut self.pending);
+        input.extend_from_slice(chunk);
+
+        let mut output = String::with_capacity(input.len());
+        let mut rest = &input[..];
+        loop {
+            match std::str::from_utf8(rest) {
+                Ok(valid) => {
+                    output.push_str(valid);
+                    break;
+                }
+                Err(err) => {
+                    let (valid, after) = rest.split_at(err.valid_up_to());
+                    // SAFETY: `valid_up_to` marks the end of a valid UTF-8 prefix.
+                    output.push_str(unsafe { std::str::from_utf8_unchecked(valid) });
+
+                    match err.error_len() {
+                        // Truncated sequence at the end of the chunk: wait for more bytes.
+                        None => {
+                            self.pending.extend_from_slice(after);
+                            break;
+                        }
+                        Some(len) => {
+                            output.push(char::REPLACEMENT_CHARACTER);
+                            rest = &after[len..];
+                        }
+                    }
+                }
+            }
+        }
+
+        output
+    }
+}
+
+pub enum OutputDecoder {
+    Utf8(Utf8Decoder),
+    Raw,
+}
+
+impl OutputDecoder {
+    pub fn new(encoding: OutputEncoding) -> Self {
+        match encoding {
+            OutputEncoding::Utf8 => OutputDecoder::Utf8(Utf8Decoder::default()),
+            OutputEncoding::Raw => OutputDecoder::Raw,
+        }
+    }
+
+    pub fn decode(&mut self, chunk: &[u8]) -> PtyData {
+        match self {
+            OutputDecoder::Utf8(decoder) => PtyData::Text(decoder.decode(chunk)),
+            OutputDecoder::Raw => PtyData::Raw(chunk.to_vec()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn decode_chunks(chunks: &[&[u8]]) -> String {
+        let mut decoder = Utf8Decoder::default();
+        chunks.iter().map(|chunk| decoder.decode(chunk)).collect()
+    }
+
+    #[test]
+    fn sequences_split_at_any_byte_decode_whole() {
+        for text in ["aéb", "a€b", "a😀b"] {
+            let bytes = text.as_bytes();
+            for split in 0..=bytes.len() {
+                let (head, tail) = bytes.split_at(split);
+                assert_eq!(decode_chunks(&[head, tail]), text, "split at {}", split);
+            }
+
+            let single_bytes: Vec<&[u8]> = bytes.chunks(1).collect();
+            assert_eq!(decode_chunks(&single_bytes), text);
+        }
+    }
+
+    #[test]
+    fn an_invalid_byte_mid_sequence_is_replaced_and_decoding_resumes() {
+        assert_eq!(decode_chunks(&[b"a\xe2\x82Ab"]), "a\u{FFFD}Ab");
+        assert_eq!(decode_chunks(&[b"a\xe2", b"\x82", b"Ab"]), "a\u{FFFD}Ab");
+        assert_eq!(
+            decode_chunks(&[b"\xf0\x9f", b"(\xf0\x9f\x98\x80"]),
+            "\u{FFFD}(😀"
+        );
+    }
+}
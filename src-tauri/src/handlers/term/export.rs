@@ -0,0 +1,161 @@
+use serde::Deserialize;
+use std::fmt::Write;
+
+use super::{
+    ansi::{render_line, strip_line, LineState},
+    screen::{Attrs, Color},
+};
+
+/// Same palette and defaults as the terminal widget, so exports look like the screen.
+const DEFAULT_FG: (u8, u8, u8) = (0x00, 0xff, 0x00);
+const DEFAULT_BG: (u8, u8, u8) = (0x00, 0x0a, 0x00);
+const BASE_COLORS: [(u8, u8, u8); 16] = [
+    (0x00, 0x00, 0x00),
+    (0xff, 0x00, 0x00),
+    (0x00, 0xff, 0x00),
+    (0xff, 0xff, 0x00),
+    (0x00, 0x00, 0xff),
+    (0xff, 0x00, 0xff),
+    (0x00, 0xff, 0xff),
+    (0xff, 0xff, 0xff),
+    (0x66, 0x66, 0x66),
+    (0xff, 0x66, 0x66),
+    (0x66, 0xff, 0x66),
+    (0xff, 0xff, 0x66),
+    (0x66, 0x66, 0xff),
+    (0xff, 0x66, 0xff),
+    (0x66, 0xff, 0xff),
+    (0xff, 0xff, 0xff),
+];
+
+#[derive(Debug, Clone, Copy, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ExportFormat {
+    Html,
+    Text,
+}
+
+impl ExportFormat {
+    pub fn extension(self) -> &'static str {
+        match self {
+            ExportFormat::Html => "html",
+            ExportFormat::Text => "txt",
+        }
+    }
+}
+
+/// xterm's 256-color palette: the 16 base colors, a 6x6x6 cube, then 24 grays.
+fn indexed_rgb(index: u8) -> (u8, u8, u8) {
+    match index {
+        0..=15 => BASE_COLORS[index as usize],
+        16..=231 => {
+            let level = |n: u8| if n == 0 { 0 } else { 55 + n * 40 };
+            let n = index - 16;
+            (level(n / 36), level(n / 6 % 6), level(n % 6))
+        }
+        _ => {
+            let gray = 8 + (index - 232) * 10;
+            (gray, gray, gray)
+        }
+    }
+}
+
+fn rgb(color: Color, default: (u8, u8, u8), bold: bool) -> (u8, u8, u8) {
+    match color {
+        Color::Default => default,
+        // Like most terminals, bold brightens the eight basic colors.
+        Color::Indexed(n) if bold && n < 8 => indexed_rgb(n + 8),
+        Color::Indexed(n) => indexed_rgb(n),
+        Color::Rgb(r, g, b) => (r, g, b),
+    }
+}
+
+fn css(attrs: &Attrs) -> String {
+    let mut fg = rgb(attrs.fg, DEFAULT_FG, attrs.bold);
+    let mut bg = rgb(attrs.bg, DEFAULT_BG, false);
+    if attrs.inverse {
+        std::mem::swap(&mut fg, &mut bg);
+    }
+
+    let mut style = String::new();
+    if fg != DEFAULT_FG {
+        let _ = write!(style, "color:#{:02x}{:02x}{:02x};", fg.0, fg.1, fg.2);
+    }
+    if bg != DEFAULT_BG {
+        let _ = write!(style, "background:#{:02x}{:02x}{:02x};", bg.0, bg.1, bg.2);
+    }
+    if attrs.bold {
+        style.push_str("font-weight:bold;");
+    }
+    if attrs.dim {
+        style.push_str("opacity:0.6;");
+    }
+    if attrs.italic {
+        style.push_str("font-style:italic;");
+    }
+    match (attrs.underline, attrs.strikethrough) {
+        (true, true) => style.push_str("text-decoration:underline line-through;"),
+        (true, false) => style.push_str("text-decoration:underline;"),
+        (false, true) => style.push_str("text-decoration:line-through;"),
+        (false, false) => {}
+    }
+    if attrs.hidden {
+        style.push_str("visibility:hidden;");
+    }
+    style
+}
+
+fn escape_into(out: &mut String, c: char) {
+    match c {
+        '&' => out.push_str("&amp;"),
+        '<' => out.push_str("&lt;"),
+        '>' => out.push_str("&gt;"),
+        '"' => out.push_str("&quot;"),
+        _ => out.push(c),
+    }
+}
+
+/// Scrollback with escapes stripped, one output line per line.
+pub fn to_text(lines: &[Vec<u8>]) -> String {
+    let mut text = String::new();
+    for line in lines {
+        text.push_str(strip_line(line).trim_end());
+        text.push('\n');
+    }
+    text
+}
+
+/// A standalone HTML page showing the scrollback with its colors and styles.
+pub fn to_html(lines: &[Vec<u8>], title: &str) -> String {
+    let mut html = String::new();
+    html.push_str("<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n<title>");
+    title.chars().for_each(|c| escape_into(&mut html, c));
+    let _ = write!(
+        html,
+        "</title>\n<style>body{{margin:0;background:#{:02x}{:02x}{:02x};}}\
+         pre{{margin:0;padding:8px;color:#{:02x}{:02x}{:02x};\
+         font-family:'Jetbrains Mono',monospace;font-size:14px;line-height:1.2;}}</style>\n\
+         </head>\n<body>\n<pre>",
+        DEFAULT_BG.0, DEFAULT_BG.1, DEFAULT_BG.2, DEFAULT_FG.0, DEFAULT_FG.1, DEFAULT_FG.2
+    );
+
+    let mut state = LineState::default();
+    for line in lines {
+        let cells = render_line(line, &mut state).cells;
+        // Runs of identically styled characters share one span.
+        for run in cells.chunk_by(|a, b| a.1 == b.1) {
+            let style = css(&run[0].1);
+            if !style.is_empty() {
+                let _ = write!(html, "<span style=\"{}\">", style);
+            }
+            run.iter().for_each(|(c, _)| escape_into(&mut html, *c));
+            if !style.is_empty() {
+                html.push_str("</span>");
+            }
+        }
+        html.push('\n');
+    }
+
+    html.push_str("</pre>\n</body>\n</html>\n");
+    html
+}
@@ -0,0 +1,375 @@
+use once_cell::sync::Lazy;
+use regex::Regex;
+use serde::Serialize;
+use std::{
+    collections::HashMap,
+    fs,
+    path::PathBuf,
+    sync::Mutex,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use super::shell_integration::FinishedCommand;
+
+/// Commands remembered from the terminal; the oldest are dropped first.
+const MAX_PTY_COMMANDS: usize = 5000;
+const DEFAULT_SUGGESTIONS: usize = 20;
+/// Commands run in the directory the palette was opened in count this much more.
+const CWD_BONUS: f64 = 2.0;
+
+const HOUR_MS: u64 = 60 * 60 * 1000;
+const DAY_MS: u64 = 24 * HOUR_MS;
+const WEEK_MS: u64 = 7 * DAY_MS;
+
+/// zsh's `EXTENDED_HISTORY` format: `: <start>:<elapsed>;<command>`.
+static ZSH_EXTENDED_RE: Lazy<Regex> = Lazy::new(|| Regex::new(r"(?s)^: *(\d+):\d+;(.*)$").unwrap());
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Shell {
+    Bash,
+    Zsh,
+    Fish,
+}
+
+/// A command as a history file or the terminal recorded it.
+#[derive(Debug, Clone)]
+struct HistoryEntry {
+    command: String,
+    /// Unix time in milliseconds, when the source keeps one.
+    time: Option<u64>,
+}
+
+#[derive(Debug, Clone, Default)]
+struct Usage {
+    count: u32,
+    last_used: Option<u64>,
+    /// How far back the last use is in its source, 0 being the newest entry.
+    /// Stands in for recency when the source keeps no timestamps.
+    age_rank: usize,
+    cwds: HashMap<String, u32>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Suggestion {
+    pub command: String,
+    pub count: u32,
+    /// Unix time in milliseconds, if any source recorded one.
+    pub last_used: Option<u64>,
+    pub score: f64,
+}
+
+/// A shell history file, parsed again only when it changes on disk.
+struct HistoryFile {
+    shell: Shell,
+    path: PathBuf,
+    modified: Option<SystemTime>,
+    usage: HashMap<String, Usage>,
+}
+
+/// Frequency- and recency-ranked index over the user's shell history files
+/// and the commands seen through the terminal's shell integration.
+pub struct CommandHistory {
+    files: Mutex<Vec<HistoryFile>>,
+    pty: Mutex<Vec<RecordedCommand>>,
+}
+
+struct RecordedCommand {
+    entry: HistoryEntry,
+    cwd: Option<String>,
+}
+
+fn now_millis() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_millis() as u64)
+        .unwrap_or(0)
+}
+
+fn home() -> Option<PathBuf> {
+    std::env::var_os("HOME").map(PathBuf::from)
+}
+
+/// The default history locations of the shells the terminal knows.
+fn history_files() -> Vec<(Shell, PathBuf)> {
+    let Some(home) = home() else {
+        return Vec::new();
+    };
+    let zdotdir = std::env::var_os("ZDOTDIR")
+        .map(PathBuf::from)
+        .unwrap_or_else(|| home.clone());
+    let data_home = std::env::var_os("XDG_DATA_HOME")
+        .map(PathBuf::from)
+        .unwrap_or_else(|| home.join(".local/share"));
+
+    vec![
+        (Shell::Bash, home.join(".bash_history")),
+        (Shell::Zsh, zdotdir.join(".zsh_history")),
+        (Shell::Zsh, zdotdir.join(".zhistory")),
+        (Shell::Fish, data_home.join("fish/fish_history")),
+    ]
+}
+
+/// With `HISTTIMEFORMAT` set, bash precedes each entry with a `#<seconds>` line.
+fn parse_bash(text: &str) -> Vec<HistoryEntry> {
+    let mut entries = Vec::new();
+    let mut time = None;
+    for line in text.lines() {
+        if let Some(seconds) = line.strip_prefix('#') {
+            if let Ok(seconds) = seconds.parse::<u64>() {
+                time = Some(seconds * 1000);
+                continue;
+            }
+        }
+        entries.push(HistoryEntry {
+            command: line.to_string(),
+            time: time.take(),
+        });
+    }
+    entries
+}
+
+/// zsh "metafies" bytes it considers special: 0x83 followed by the byte xor 32.
+fn unmetafy(bytes: &[u8]) -> Vec<u8> {
+    let mut out = Vec::with_capacity(bytes.len());
+    let mut iter = bytes.iter();
+    while let Some(&byte) = iter.next() {
+        if byte == 0x83 {
+            if let Some(&next) = iter.next() {
+                out.push(next ^ 32);
+            }
+        } else {
+            out.push(byte);
+        }
+    }
+    out
+}
+
+/// Plain or extended format; a trailing backslash continues a multi-line command.
+fn parse_zsh(bytes: &[u8]) -> Vec<HistoryEntry> {
+    let text = String::from_utf8_lossy(&unmetafy(bytes)).into_owned();
+    let mut entries = Vec::new();
+    let mut pending = String::new();
+    for line in text.lines() {
+        if let Some(continued) = line.strip_suffix('\\') {
+            pending.push_str(continued);
+            pending.push('\n');
+            continue;
+        }
+        pending.push_str(line);
+        let record = std::mem::take(&mut pending);
+
+        match ZSH_EXTENDED_RE.captures(&record) {
+            Some(captures) => entries.push(HistoryEntry {
+                command: captures[2].to_string(),
+                time: captures[1].parse::<u64>().ok().map(|s| s * 1000),
+            }),
+            None => entries.push(HistoryEntry {
+                command: record,
+                time: None,
+            }),
+        }
+    }
+    entries
+}
+
+fn unescape_fish(command: &str) -> String {
+    let mut out = String::with_capacity(command.len());
+    let mut chars = command.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            out.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('n') => out.push('\n'),
+            Some('\\') => out.push('\\'),
+            Some(other) => {
+                out.push('\\');
+                out.push(other);
+            }
+            None => out.push('\\'),
+        }
+    }
+    out
+}
+
+/// fish keeps a YAML-like list of `- cmd: ...` items with a `when:` field.
+fn parse_fish(text: &str) -> Vec<HistoryEntry> {
+    let mut entries: Vec<HistoryEntry> = Vec::new();
+    for line in text.lines() {
+        if let Some(command) = line.strip_prefix("- cmd: ") {
+            entries.push(HistoryEntry {
+                command: unescape_fish(command),
+                time: None,
+            });
+        } else if let Some(seconds) = line.trim_start().strip_prefix("when: ") {
+            if let Some(last) = entries.last_mut() {
+                last.time = seconds.trim().parse::<u64>().ok().map(|s| s * 1000);
+            }
+        }
+    }
+    entries
+}
+
+/// Folds entries, oldest first, into per-command usage.
+fn tally<'a>(
+    entries: impl DoubleEndedIterator<Item = (&'a HistoryEntry, Option<&'a str>)>,
+) -> HashMap<String, Usage> {
+    let mut usage: HashMap<String, Usage> = HashMap::new();
+    for (age_rank, (entry, cwd)) in entries.rev().enumerate() {
+        let command = entry.command.trim();
+        if command.is_empty() {
+            continue;
+        }
+        let usage = usage.entry(command.to_string()).or_insert_with(|| Usage {
+            age_rank,
+            ..Default::default()
+        });
+        usage.count += 1;
+        usage.last_used = usage.last_used.max(entry.time);
+        if let Some(cwd) = cwd {
+            *usage.cwds.entry(cwd.to_string()).or_default() += 1;
+        }
+    }
+    usage
+}
+
+impl HistoryFile {
+    fn refresh(&mut self) {
+        let modified = fs::metadata(&self.path).and_then(|m| m.modified()).ok();
+        if modified.is_some() && modified == self.modified {
+            return;
+        }
+        self.modified = modified;
+
+        let Ok(bytes) = fs::read(&self.path) else {
+            self.usage.clear();
+            return;
+        };
+        let entries = match self.shell {
+            Shell::Bash => parse_bash(&String::from_utf8_lossy(&bytes)),
+            Shell::Zsh => parse_zsh(&bytes),
+            Shell::Fish => parse_fish(&String::from_utf8_lossy(&bytes)),
+        };
+        self.usage = tally(entries.iter().map(|entry| (entry, None)));
+    }
+}
+
+/// Weight of a use by how long ago it was, or how many commands ago when
+/// the source has no timestamps.
+fn recency(usage: &Usage, now: u64) -> f64 {
+    match usage.last_used {
+        Some(time) => match now.saturating_sub(time) {
+            age if age < HOUR_MS => 4.0,
+            age if age < DAY_MS => 2.0,
+            age if age < WEEK_MS => 0.5,
+            _ => 0.25,
+        },
+        None => match usage.age_rank {
+            rank if rank < 50 => 4.0,
+            rank if rank < 500 => 2.0,
+            rank if rank < 5000 => 0.5,
+            _ => 0.25,
+        },
+    }
+}
+
+impl CommandHistory {
+    pub fn new() -> Self {
+        CommandHistory {
+            files: Mutex::new(
+                history_files()
+                    .into_iter()
+                    .map(|(shell, path)| HistoryFile {
+                        shell,
+                        path,
+                        modified: None,
+                        usage: HashMap::new(),
+                    })
+                    .collect(),
+            ),
+            pty: Mutex::new(Vec::new()),
+        }
+    }
+
+    /// Remembers a command the shell integration saw finish in the terminal.
+    pub fn record(&self, command: &FinishedCommand) {
+        let mut pty = self.pty.lock().unwrap();
+        pty.push(RecordedCommand {
+            entry: HistoryEntry {
+                command: command.command.clone(),
+                time: Some(command.finished_at),
+            },
+            cwd: command.cwd.clone(),
+        });
+        if pty.len() > MAX_PTY_COMMANDS {
+            let excess = pty.len() - MAX_PTY_COMMANDS;
+            pty.drain(..excess);
+        }
+    }
+
+    /// Commands starting with `prefix`, best first. Ones run in `cwd` rank higher.
+    pub fn suggest(
+        &self,
+        prefix: &str,
+        cwd: Option<&str>,
+        limit: Option<usize>,
+    ) -> Vec<Suggestion> {
+        let mut merged: HashMap<String, Usage> = HashMap::new();
+        let mut merge = |usage: &HashMap<String, Usage>| {
+            for (command, usage) in usage {
+                if !command.starts_with(prefix) {
+                    continue;
+                }
+                let total = merged.entry(command.clone()).or_insert_with(|| Usage {
+                    age_rank: usize::MAX,
+                    ..Default::default()
+                });
+                total.count += usage.count;
+                total.last_used = total.last_used.max(usage.last_used);
+                total.age_rank = total.age_rank.min(usage.age_rank);
+                for (dir, count) in &usage.cwds {
+                    *total.cwds.entry(dir.clone()).or_default() += count;
+                }
+            }
+        };
+
+        for file in self.files.lock().unwrap().iter_mut() {
+            file.refresh();
+            merge(&file.usage);
+        }
+        let pty = self.pty.lock().unwrap();
+        let recorded = tally(
+            pty.iter()
+                .map(|recorded| (&recorded.entry, recorded.cwd.as_deref())),
+        );
+        drop(pty);
+        merge(&recorded);
+
+        let now = now_millis();
+        let mut suggestions: Vec<Suggestion> = merged
+            .into_iter()
+            .map(|(command, usage)| {
+                let mut score = usage.count as f64 * recency(&usage, now);
+                if cwd.is_some_and(|cwd| usage.cwds.contains_key(cwd)) {
+                    score *= CWD_BONUS;
+                }
+                Suggestion {
+                    command,
+                    count: usage.count,
+                    last_used: usage.last_used,
+                    score,
+                }
+            })
+            .collect();
+
+        suggestions.sort_by(|a, b| {
+            b.score
+                .total_cmp(&a.score)
+                .then_with(|| b.last_used.cmp(&a.last_used))
+                .then_with(|| a.command.cmp(&b.command))
+        });
+        suggestions.truncate(limit.unwrap_or(DEFAULT_SUGGESTIONS));
+        suggestions
+    }
+}
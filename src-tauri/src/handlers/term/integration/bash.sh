@@ -0,0 +1,40 @@
+# sci-fi-ui shell integration for bash >= 4.4: OSC 133 command marks and OSC 7 cwd.
+# Loaded through --init-file, so it first sources what bash would have read itself.
+
+if [[ -n "$SCI_FI_UI_LOGIN" ]]; then
+    unset SCI_FI_UI_LOGIN
+    [[ -r /etc/profile ]] && . /etc/profile
+    for __scifi_rc in ~/.bash_profile ~/.bash_login ~/.profile; do
+        if [[ -r "$__scifi_rc" ]]; then
+            . "$__scifi_rc"
+            break
+        fi
+    done
+    unset __scifi_rc
+elif [[ -r ~/.bashrc ]]; then
+    . ~/.bashrc
+fi
+
+__scifi_precmd() {
+    local status=$?
+    printf '\e]133;D;%s\a' "$status"
+    printf '\e]7;file://%s%s\a' "$HOSTNAME" "$PWD"
+}
+
+# Runs inside PS0, i.e. after the command line is read and before it executes.
+__scifi_preexec() {
+    local cmd
+    cmd=$(HISTTIMEFORMAT= builtin history 1)
+    cmd="${cmd#"${cmd%%[![:space:]]*}"}"
+    cmd="${cmd#*[[:space:]]}"
+    cmd="${cmd#"${cmd%%[![:space:]]*}"}"
+    printf '\e]133;C;cmdline=%s\a' "${cmd//[[:cntrl:]]/ }"
+}
+
+if [[ "$(declare -p PROMPT_COMMAND 2>/dev/null)" == "declare -a"* ]]; then
+    PROMPT_COMMAND=(__scifi_precmd "${PROMPT_COMMAND[@]}")
+else
+    PROMPT_COMMAND="__scifi_precmd${PROMPT_COMMAND:+; $PROMPT_COMMAND}"
+fi
+PS0='$(__scifi_preexec)'"$PS0"
+PS1='\[\e]133;A\a\]'"$PS1"'\[\e]133;B\a\]'
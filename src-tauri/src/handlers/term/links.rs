@@ -0,0 +1,249 @@
+use once_cell::sync::Lazy;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+
+use super::ansi::{render_line, LineState};
+
+/// Lines longer than this (minified JSON, base64 blobs) are not scanned.
+const MAX_SCANNED_LINE: usize = 4096;
+/// Path candidates checked against the filesystem per line.
+const MAX_PATH_CANDIDATES: usize = 16;
+const MAX_PENDING_LINE: usize = 64 * 1024;
+
+static URL_RE: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r#"\b(?i:https?|ftp|file)://[^\s<>"'`]+"#).unwrap());
+
+/// `/abs/path`, `./rel`, `~/x`, `dir/file` or `file.ext`, optionally followed by `:line[:col]`.
+static PATH_RE: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(
+        r"(?P<path>(?:~|\.{1,2})?(?:/[\w.@+-]+)+/?|[\w.@+-]+(?:/[\w.@+-]+)+/?|[\w@+-][\w.@+-]*\.[A-Za-z0-9]{1,10})(?::(?P<line>\d+)(?::(?P<col>\d+))?)?",
+    )
+    .unwrap()
+});
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LinkKind {
+    Url,
+    File,
+}
+
+/// A clickable span; `column` and `length` count characters of the ANSI-stripped line.
+#[derive(Debug, Clone, Serialize)]
+pub struct Link {
+    pub column: usize,
+    pub length: usize,
+    /// The span's text as displayed.
+    pub text: String,
+    pub kind: LinkKind,
+    /// The URL, or the file's absolute path.
+    pub target: String,
+    pub is_dir: bool,
+    pub line_number: Option<u32>,
+    pub column_number: Option<u32>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct LineLinks {
+    /// Absolute line number, as used by `search_session`.
+    pub line: u64,
+    pub links: Vec<Link>,
+}
+
+/// Finds URLs, OSC 8 hyperlinks and existing file paths in a session's output.
+#[derive(Default)]
+pub struct LinkDetector {
+    /// Directory relative paths are resolved against; kept up to date by the status poller.
+    pub cwd: Option<PathBuf>,
+    state: LineState,
+    pending: Vec<u8>,
+    line: u64,
+}
+
+fn char_column(text: &str, byte: usize) -> usize {
+    text[..byte].chars().count()
+}
+
+fn trim_url(url: &str) -> &str {
+    let mut url = url.trim_end_matches(['.', ',', ';', ':', '!', '?', '\'', '"']);
+    // A closing bracket belongs to the URL only if it opened one, as in Wikipedia links.
+    for (open, close) in [('(', ')'), ('[', ']'), ('{', '}')] {
+        while url.ends_with(close) && url.matches(open).count() < url.matches(close).count() {
+            url = &url[..url.len() - 1];
+        }
+    }
+    url
+}
+
+/// The local path a `file://` URL points at; any host part is dropped.
+/// `None` for every other scheme.
+pub fn file_url_path(url: &str) -> Option<String> {
+    let scheme = url.get(..7)?;
+    if !scheme.eq_ignore_ascii_case("file://") {
+        return None;
+    }
+    let rest = &url[7..];
+    let path = rest.find('/').map_or("/", |i| &rest[i..]);
+    Some(urlencoding::decode(path).map_or_else(|_| path.to_string(), |p| p.into_owned()))
+}
+
+fn expand_home(path: &str) -> Option<PathBuf> {
+    match path.strip_prefix('~') {
+        Some(rest) => {
+            let home = std::env::var_os("HOME")?;
+            Some(PathBuf::from(home).join(rest.trim_start_matches('/')))
+        }
+        None => Some(PathBuf::from(path)),
+    }
+}
+
+fn resolve(path: &str, cwd: Option<&Path>) -> Option<PathBuf> {
+    let path = expand_home(path)?;
+    let path: PathBuf = if path.is_absolute() {
+        path.components().collect()
+    } else {
+        cwd?.join(path).components().collect()
+    };
+    path.exists().then_some(path)
+}
+
+impl LinkDetector {
+    /// Feeds output and returns the links found on the lines it completed.
+    /// `fallback_cwd` is used while the status poller has not reported a directory.
+    pub fn feed(&mut self, bytes: &[u8], fallback_cwd: Option<&Path>) -> Vec<LineLinks> {
+        let mut found = Vec::new();
+        let mut rest = bytes;
+        while let Some(end) = rest.iter().position(|&b| b == b'\n') {
+            self.pending.extend_from_slice(&rest[..end + 1]);
+            rest = &rest[end + 1..];
+
+            let line = std::mem::take(&mut self.pending);
+            let links = self.scan_line(&line, fallback_cwd);
+            if !links.is_empty() {
+                found.push(LineLinks {
+                    line: self.line,
+                    links,
+                });
+            }
+            self.line += 1;
+        }
+
+        self.pending.extend_from_slice(rest);
+        // Keep the line count right without holding an endless line in memory.
+        if self.pending.len() > MAX_PENDING_LINE {
+            self.pending.clear();
+        }
+        found
+    }
+
+    /// Counts the lines of output without looking for links in them, e.g. for
+    /// restored history, so the lines after it keep their numbers.
+    pub fn skip(&mut self, bytes: &[u8]) {
+        let mut rest = bytes;
+        while let Some(end) = rest.iter().position(|&b| b == b'\n') {
+            rest = &rest[end + 1..];
+            self.pending.clear();
+            self.line += 1;
+        }
+
+        self.pending.extend_from_slice(rest);
+        if self.pending.len() > MAX_PENDING_LINE {
+            self.pending.clear();
+        }
+    }
+
+    fn scan_line(&mut self, raw: &[u8], fallback_cwd: Option<&Path>) -> Vec<Link> {
+        let rendered = render_line(raw, &mut self.state);
+        let text: String = rendered.cells.iter().map(|(c, _)| c).collect();
+        let mut links: Vec<Link> = rendered
+            .hyperlinks
+            .into_iter()
+            .map(|link| {
+                let text: String = text
+                    .chars()
+                    .skip(link.start)
+                    .take(link.end - link.start)
+                    .collect();
+                let file = file_url_path(&link.uri);
+                Link {
+                    column: link.start,
+                    length: link.end - link.start,
+                    text,
+                    kind: if file.is_some() {
+                        LinkKind::File
+                    } else {
+                        LinkKind::Url
+                    },
+                    is_dir: file.as_deref().is_some_and(|path| Path::new(path).is_dir()),
+                    target: file.unwrap_or(link.uri),
+                    line_number: None,
+                    column_number: None,
+                }
+            })
+            .collect();
+
+        if text.len() > MAX_SCANNED_LINE {
+            return links;
+        }
+        let overlaps = |links: &[Link], start: usize, end: usize| {
+            links
+                .iter()
+                .any(|link| start < link.column + link.length && link.column < end)
+        };
+
+        for found in URL_RE.find_iter(&text) {
+            let url = trim_url(found.as_str());
+            let column = char_column(&text, found.start());
+            let length = url.chars().count();
+            if overlaps(&links, column, column + length) {
+                continue;
+            }
+            // Files are shown in the explorer, never handed to the system opener.
+            let file = file_url_path(url);
+            links.push(Link {
+                column,
+                length,
+                text: url.to_string(),
+                kind: if file.is_some() {
+                    LinkKind::File
+                } else {
+                    LinkKind::Url
+                },
+                is_dir: file.as_deref().is_some_and(|path| Path::new(path).is_dir()),
+                target: file.unwrap_or_else(|| url.to_string()),
+                line_number: None,
+                column_number: None,
+            });
+        }
+
+        let cwd = self.cwd.as_deref().or(fallback_cwd);
+        for captures in PATH_RE.captures_iter(&text).take(MAX_PATH_CANDIDATES) {
+            let whole = captures.get(0).unwrap();
+            let path = captures.name("path").unwrap();
+            let column = char_column(&text, whole.start());
+            let length = whole.as_str().chars().count();
+            if overlaps(&links, column, column + length) {
+                continue;
+            }
+            let Some(resolved) = resolve(path.as_str(), cwd) else {
+                continue;
+            };
+
+            let number = |name| captures.name(name).and_then(|m| m.as_str().parse().ok());
+            links.push(Link {
+                column,
+                length,
+                text: whole.as_str().to_string(),
+                kind: LinkKind::File,
+                is_dir: resolved.is_dir(),
+                target: resolved.to_string_lossy().into_owned(),
+                line_number: number("line"),
+                column_number: number("col"),
+            });
+        }
+
+        links.sort_by_key(|link| link.column);
+        links
+    }
+}
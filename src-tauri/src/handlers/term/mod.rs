@@ -0,0 +1,477 @@
+// Prevents additional console window on Windows in release, DO NOT REMOVE!!
+#![cfg_attr(not(debug_assertions), windows_subsystem = "windows")]
+
+pub mod ansi;
+#[cfg(unix)]
+pub mod daemon;
+pub mod decoder;
+pub mod export;
+pub mod history;
+pub mod links;
+pub mod recording;
+pub mod screen;
+pub mod scrollback;
+pub mod search;
+pub mod session;
+pub mod shell_integration;
+pub mod signal;
+pub mod status;
+pub mod stream;
+pub mod triggers;
+
+use portable_pty::PtySize;
+use regex::RegexBuilder;
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+
+use tauri::{AppHandle, Emitter, State};
+use tauri_plugin_dialog::DialogExt;
+use tauri_plugin_opener::OpenerExt;
+use tokio::sync::oneshot;
+
+use crate::handlers::config::shell_profiles::{load_shell_profile, DEFAULT_PROFILE};
+use crate::handlers::config::triggers::{read_trigger_rules, TriggerRule};
+use crate::AppState;
+use decoder::OutputEncoding;
+use export::ExportFormat;
+use history::Suggestion;
+use links::LinkKind;
+use recording::Recorder;
+use screen::ScreenSnapshot;
+use scrollback::ScrollbackLimit;
+use search::{search_lines, SearchDirection, SearchMatch};
+use session::SessionId;
+use shell_integration::FinishedCommand;
+use signal::{signal_foreground, SessionSignal, SignalReport};
+use status::SessionStatus;
+use stream::OutputSnapshot;
+use triggers::TriggerEngine;
+
+const DEFAULT_MAX_SEARCH_RESULTS: usize = 1000;
+#[derive(Serialize, Clone)]
+struct ExplorerNavigate {
+    path: String,
+    /// File to highlight in `path`, when the link pointed at a file.
+    selected: Option<String>,
+}
+
+/// Used until the widget reports its real size.
+const DEFAULT_ROWS: u16 = 24;
+const DEFAULT_COLS: u16 = 80;
+
+#[derive(Debug, Deserialize)]
+pub struct TerminalSize {
+    rows: u16,
+    cols: u16,
+    pixel_width: Option<u16>,
+    pixel_height: Option<u16>,
+}
+
+fn pty_size(
+    rows: u16,
+    cols: u16,
+    pixel_width: Option<u16>,
+    pixel_height: Option<u16>,
+) -> Result<PtySize, String> {
+    if rows == 0 || cols == 0 {
+        return Err(format!("Invalid terminal size: {}x{}", cols, rows));
+    }
+    Ok(PtySize {
+        rows,
+        cols,
+        pixel_width: pixel_width.unwrap_or(0),
+        pixel_height: pixel_height.unwrap_or(0),
+    })
+}
+
+/// Reads the trigger rules for a session. Broken rules in the settings cost
+/// the session its triggers, never the shell itself.
+fn trigger_rules_or_none(app: &AppHandle) -> Vec<TriggerRule> {
+    read_trigger_rules(app).unwrap_or_else(|err| {
+        eprintln!("Starting the shell without triggers: {}", err);
+        Vec::new()
+    })
+}
+
+/// Starts a session from the named shell profile, or the default shell, at
+/// the size the widget measured (80x24 if it has not been laid out yet).
+/// `triggers` limits the session to the trigger rules with those names.
+#[tauri::command]
+pub async fn async_create_shell(
+    app: AppHandle,
+    profile: Option<String>,
+    encoding: Option<OutputEncoding>,
+    scrollback: Option<ScrollbackLimit>,
+    size: Option<TerminalSize>,
+    triggers: Option<Vec<String>>,
+    state: State<'_, AppState>,
+) -> Result<SessionId, String> {
+    let size = match size {
+        Some(size) => pty_size(size.rows, size.cols, size.pixel_width, size.pixel_height)?,
+        None => pty_size(DEFAULT_ROWS, DEFAULT_COLS, None, None)?,
+    };
+    let profile = load_shell_profile(&app, profile.as_deref().unwrap_or(DEFAULT_PROFILE))?;
+    let mut cmd = profile.to_command();
+    if profile.shell_integration {
+        shell_integration::inject(&app, &mut cmd)?;
+    }
+    let rules = trigger_rules_or_none(&app);
+    let mut engine = TriggerEngine::new(&profile.name, &rules);
+    engine.set_scope(triggers, &rules);
+
+    state
+        .sessions
+        .create(
+            &app,
+            cmd,
+            size,
+            encoding.unwrap_or_default(),
+            scrollback.unwrap_or_default(),
+            engine,
+        )
+        .await
+}
+
+/// Limits a running session to the trigger rules named in `rules`, or lets it
+/// run all of its profile's rules again on `None`.
+#[tauri::command]
+pub async fn set_session_triggers(
+    app: AppHandle,
+    session_id: SessionId,
+    rules: Option<Vec<String>>,
+    state: State<'_, AppState>,
+) -> Result<(), String> {
+    let output = state.sessions.get(session_id).await?.output.clone();
+    let all = read_trigger_rules(&app)?;
+    output.triggers.lock().unwrap().set_scope(rules, &all);
+    Ok(())
+}
+
+/// Replays the session's scrollback to a (re)mounted widget, which then keeps
+/// listening to `pty_output` from the returned offset on.
+#[tauri::command]
+pub async fn attach_session(
+    session_id: SessionId,
+    state: State<'_, AppState>,
+) -> Result<OutputSnapshot, String> {
+    let output = state.sessions.output(session_id).await?;
+    output.flow.reset();
+    Ok(output.snapshot())
+}
+
+/// Terminates the shell; the widget learns about it through `session_exited`.
+#[tauri::command]
+pub async fn async_close_shell(
+    session_id: SessionId,
+    state: State<'_, AppState>,
+) -> Result<(), String> {
+    state.sessions.kill(session_id).await
+}
+
+#[tauri::command]
+pub async fn async_write_to_pty(
+    session_id: SessionId,
+    data: &str,
+    state: State<'_, AppState>,
+) -> Result<(), String> {
+    state.sessions.write(session_id, data.as_bytes()).await
+}
+
+/// Mirrors input typed into any of `session_ids` to all of them; an empty
+/// list (or a single session) ends the broadcast.
+#[tauri::command]
+pub async fn broadcast_input(
+    app: AppHandle,
+    session_ids: Vec<SessionId>,
+    state: State<'_, AppState>,
+) -> Result<Vec<SessionId>, String> {
+    state.sessions.set_broadcast(&app, session_ids).await
+}
+
+#[tauri::command]
+pub async fn get_broadcast_group(state: State<'_, AppState>) -> Result<Vec<SessionId>, String> {
+    Ok(state.sessions.broadcast_group().await)
+}
+
+/// Sends `signal` to the session's foreground process group, e.g. to stop a
+/// program that has raw mode on and ignores Ctrl+C.
+#[tauri::command]
+pub async fn signal_session(
+    session_id: SessionId,
+    signal: SessionSignal,
+    state: State<'_, AppState>,
+) -> Result<SignalReport, String> {
+    let session = state.sessions.get(session_id).await?;
+    let master = session.master.lock().await;
+    signal_foreground(&**master, signal)
+}
+
+/// Latest `session_status` of a session, for widgets that attach between changes.
+#[tauri::command]
+pub async fn get_session_status(
+    session_id: SessionId,
+    state: State<'_, AppState>,
+) -> Result<Option<SessionStatus>, String> {
+    let session = state.sessions.get(session_id).await?;
+    let status = session.status.lock().unwrap().clone();
+    Ok(status)
+}
+
+/// Why the session's shell will not survive the app, or `None` when it lives
+/// in the session daemon.
+#[tauri::command]
+pub async fn get_session_fallback(
+    session_id: SessionId,
+    state: State<'_, AppState>,
+) -> Result<Option<String>, String> {
+    let session = state.sessions.get(session_id).await?;
+    Ok(session.local_reason.clone())
+}
+
+/// Called by the terminal widget once it has rendered a `pty_output` chunk.
+#[tauri::command]
+pub async fn ack_pty_output(
+    session_id: SessionId,
+    bytes: usize,
+    state: State<'_, AppState>,
+) -> Result<(), String> {
+    state.sessions.output(session_id).await?.flow.ack(bytes);
+    Ok(())
+}
+
+/// Pixel dimensions are passed on to the PTY for TUI apps and image protocols.
+#[tauri::command]
+pub async fn async_resize_pty(
+    session_id: SessionId,
+    rows: u16,
+    cols: u16,
+    pixel_width: Option<u16>,
+    pixel_height: Option<u16>,
+    state: State<'_, AppState>,
+) -> Result<(), String> {
+    let size = pty_size(rows, cols, pixel_width, pixel_height)?;
+    let session = state.sessions.get(session_id).await?;
+    let master = session.master.lock().await;
+    master
+        .resize(size)
+        .map_err(|err| format!("Failed to resize session {}: {}", session_id, err))?;
+
+    session.output.screen.lock().unwrap().resize(rows, cols);
+    session.output.record_resize(cols, rows);
+    Ok(())
+}
+
+/// What the session currently shows, as the backend's VT model sees it.
+#[tauri::command]
+pub async fn get_screen_snapshot(
+    session_id: SessionId,
+    state: State<'_, AppState>,
+) -> Result<ScreenSnapshot, String> {
+    let output = state.sessions.output(session_id).await?;
+    let snapshot = output.screen.lock().unwrap().snapshot();
+    Ok(snapshot)
+}
+
+/// Starts writing the session to an asciicast v2 file, replacing any recording in progress.
+#[tauri::command]
+pub async fn start_recording(
+    session_id: SessionId,
+    path: String,
+    state: State<'_, AppState>,
+) -> Result<(), String> {
+    let session = state.sessions.get(session_id).await?;
+    let size = session
+        .master
+        .lock()
+        .await
+        .get_size()
+        .map_err(|err| err.to_string())?;
+
+    let recorder =
+        Recorder::create(Path::new(&path), size.cols, size.rows).map_err(|err| err.to_string())?;
+    let previous = session.output.recorder.lock().unwrap().replace(recorder);
+    if let Some(previous) = previous {
+        previous.finish().map_err(|err| err.to_string())?;
+    }
+    Ok(())
+}
+
+#[tauri::command]
+pub async fn stop_recording(
+    session_id: SessionId,
+    state: State<'_, AppState>,
+) -> Result<(), String> {
+    let session = state.sessions.get(session_id).await?;
+    let recorder = session.output.recorder.lock().unwrap().take();
+    match recorder {
+        Some(recorder) => recorder.finish().map_err(|err| err.to_string()),
+        None => Err("Session is not being recorded".to_string()),
+    }
+}
+
+/// Plays a `.cast` file back as a new session; `speed` 2.0 plays twice as fast.
+#[tauri::command]
+pub async fn replay_recording(
+    app: AppHandle,
+    path: String,
+    speed: Option<f64>,
+    state: State<'_, AppState>,
+) -> Result<SessionId, String> {
+    state
+        .sessions
+        .replay(&app, Path::new(&path), speed.unwrap_or(1.0))
+        .await
+}
+
+/// Regex search over the session's scrollback with escapes stripped.
+#[tauri::command]
+pub async fn search_session(
+    session_id: SessionId,
+    pattern: String,
+    case_sensitive: Option<bool>,
+    direction: Option<SearchDirection>,
+    start_line: Option<u64>,
+    max_results: Option<usize>,
+    state: State<'_, AppState>,
+) -> Result<Vec<SearchMatch>, String> {
+    let regex = RegexBuilder::new(&pattern)
+        .case_insensitive(!case_sensitive.unwrap_or(false))
+        .build()
+        .map_err(|err| err.to_string())?;
+
+    let output = state.sessions.output(session_id).await?;
+    let (first_line, lines) = {
+        let scrollback = output.scrollback.lock().unwrap();
+        let lines: Vec<Vec<u8>> = scrollback.lines().map(<[u8]>::to_vec).collect();
+        (scrollback.first_line(), lines)
+    };
+
+    tauri::async_runtime::spawn_blocking(move || {
+        search_lines(
+            &lines,
+            first_line,
+            &regex,
+            direction.unwrap_or_default(),
+            start_line,
+            max_results.unwrap_or(DEFAULT_MAX_SEARCH_RESULTS),
+        )
+    })
+    .await
+    .map_err(|err| err.to_string())
+}
+
+/// Commands the shell reported through its prompt marks, oldest first.
+#[tauri::command]
+pub async fn list_session_commands(
+    session_id: SessionId,
+    state: State<'_, AppState>,
+) -> Result<Vec<FinishedCommand>, String> {
+    let output = state.sessions.output(session_id).await?;
+    let history = output.integration.lock().unwrap().history();
+    Ok(history)
+}
+
+/// Past commands starting with `prefix`, ranked by how often and how recently
+/// they were run, from the shell history files and the terminal itself.
+#[tauri::command]
+pub async fn suggest_commands(
+    prefix: String,
+    cwd: Option<String>,
+    limit: Option<usize>,
+    state: State<'_, AppState>,
+) -> Result<Vec<Suggestion>, String> {
+    Ok(state.history.suggest(&prefix, cwd.as_deref(), limit))
+}
+
+/// Saves the session's scrollback as HTML (colors kept) or plain text to a file
+/// the user picks. Returns the path, or `None` if the dialog was cancelled.
+#[tauri::command]
+pub async fn export_session(
+    app: AppHandle,
+    session_id: SessionId,
+    format: ExportFormat,
+    state: State<'_, AppState>,
+) -> Result<Option<String>, String> {
+    let output = state.sessions.output(session_id).await?;
+    let lines: Vec<Vec<u8>> = output
+        .scrollback
+        .lock()
+        .unwrap()
+        .lines()
+        .map(<[u8]>::to_vec)
+        .collect();
+
+    let extension = format.extension();
+    // The blocking variant would tie up an async worker for as long as the dialog is open.
+    let (picked, picked_rx) = oneshot::channel();
+    app.dialog()
+        .file()
+        .set_title("Export terminal session")
+        .set_file_name(format!("session-{}.{}", session_id, extension))
+        .add_filter(extension.to_uppercase(), &[extension])
+        .save_file(move |path| {
+            let _ = picked.send(path);
+        });
+    let Some(path) = picked_rx.await.map_err(|err| err.to_string())? else {
+        return Ok(None);
+    };
+    let path = path.into_path().map_err(|err| err.to_string())?;
+
+    let contents = tauri::async_runtime::spawn_blocking(move || match format {
+        ExportFormat::Html => export::to_html(&lines, &format!("Terminal session {}", session_id)),
+        ExportFormat::Text => export::to_text(&lines),
+    })
+    .await
+    .map_err(|err| err.to_string())?;
+
+    std::fs::write(&path, contents).map_err(|err| format!("{}: {}", path.display(), err))?;
+    Ok(Some(path.to_string_lossy().into_owned()))
+}
+
+/// Schemes `open_link` hands to the default browser; anything else in terminal
+/// output (`javascript:`, custom app schemes, ...) is refused.
+const BROWSER_SCHEMES: [&str; 4] = ["http", "https", "ftp", "mailto"];
+
+/// Opens a link from `terminal_links`: URLs in the default browser, files and
+/// directories in the file explorer (via `explorer_navigate`). A `file://`
+/// URL always counts as a file, whatever `kind` says.
+#[tauri::command]
+pub fn open_link(app: AppHandle, kind: LinkKind, target: String) -> Result<(), String> {
+    let (kind, target) = match links::file_url_path(&target) {
+        Some(path) => (LinkKind::File, path),
+        None => (kind, target),
+    };
+    match kind {
+        LinkKind::Url => {
+            let scheme = target.split_once(':').map_or("", |(scheme, _)| scheme);
+            if !BROWSER_SCHEMES
+                .iter()
+                .any(|allowed| scheme.eq_ignore_ascii_case(allowed))
+            {
+                return Err(format!("Refusing to open {}: unsupported scheme", target));
+            }
+            app.opener()
+                .open_url(target, None::<&str>)
+                .map_err(|err| err.to_string())
+        }
+        LinkKind::File => {
+            let path = Path::new(&target);
+            if !path.exists() {
+                return Err(format!("{} no longer exists", target));
+            }
+            let (dir, selected) = if path.is_dir() {
+                (path, None)
+            } else {
+                (path.parent().unwrap_or(path), Some(target.clone()))
+            };
+            app.emit(
+                "explorer_navigate",
+                ExplorerNavigate {
+                    path: dir.to_string_lossy().into_owned(),
+                    selected,
+                },
+            )
+            .map_err(|err| err.to_string())
+        }
+    }
+}
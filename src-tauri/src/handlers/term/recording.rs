@@ -0,0 +1,229 @@
+use serde::Serialize;
+use serde_json::Value;
+use std::{
+    fs::File,
+    io::{self, BufRead, BufReader, BufWriter, Read, Write},
+    path::Path,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    thread,
+    time::{Duration, Instant, SystemTime, UNIX_EPOCH},
+};
+
+use super::decoder::Utf8Decoder;
+
+/// The longest a paused replay sleeps before checking whether it was cancelled.
+const CANCEL_POLL_INTERVAL: Duration = Duration::from_millis(50);
+/// Replay speeds outside this range are clamped to it.
+const MIN_SPEED: f64 = 0.01;
+const MAX_SPEED: f64 = 100.0;
+
+#[derive(Serialize)]
+struct CastHeader {
+    version: u8,
+    width: u16,
+    height: u16,
+    timestamp: u64,
+    env: CastEnv,
+}
+
+#[derive(Serialize)]
+struct CastEnv {
+    #[serde(rename = "TERM")]
+    term: &'static str,
+}
+
+/// Writes a session's output and resizes as an asciicast v2 `.cast` file.
+pub struct Recorder {
+    file: BufWriter<File>,
+    started: Instant,
+    decoder: Utf8Decoder,
+}
+
+impl Recorder {
+    pub fn create(path: &Path, cols: u16, rows: u16) -> io::Result<Self> {
+        let mut file = BufWriter::new(File::create(path)?);
+        let header = CastHeader {
+            version: 2,
+            width: cols,
+            height: rows,
+            timestamp: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map(|d| d.as_secs())
+                .unwrap_or(0),
+            env: CastEnv {
+                term: "xterm-256color",
+            },
+        };
+        serde_json::to_writer(&mut file, &header)?;
+        file.write_all(b"\n")?;
+
+        Ok(Recorder {
+            file,
+            started: Instant::now(),
+            decoder: Utf8Decoder::default(),
+        })
+    }
+
+    fn event(&mut self, code: &str, data: &str) -> io::Result<()> {
+        let time = self.started.elapsed().as_micros() as f64 / 1_000_000.0;
+        serde_json::to_writer(&mut self.file, &(time, code, data))?;
+        self.file.write_all(b"\n")
+    }
+
+    pub fn output(&mut self, chunk: &[u8]) -> io::Result<()> {
+        // asciicast stores text, so characters split across reads are held back.
+        let text = self.decoder.decode(chunk);
+        if text.is_empty() {
+            return Ok(());
+        }
+        self.event("o", &text)
+    }
+
+    pub fn resize(&mut self, cols: u16, rows: u16) -> io::Result<()> {
+        self.event("r", &format!("{}x{}", cols, rows))
+    }
+
+    pub fn finish(mut self) -> io::Result<()> {
+        self.file.flush()
+    }
+}
+
+/// Plays the output events of a `.cast` file back with their original timing
+/// (divided by `speed`), so it can stand in for a PTY reader. Resize events
+/// go to the hook set with `on_resize` when they are due.
+pub struct CastPlayer {
+    lines: io::Lines<BufReader<File>>,
+    cols: u16,
+    rows: u16,
+    started: Instant,
+    speed: f64,
+    pending: Vec<u8>,
+    cancelled: Arc<AtomicBool>,
+    resized: Option<Box<dyn FnMut(u16, u16) + Send>>,
+}
+
+impl CastPlayer {
+    pub fn open(path: &Path, speed: f64, cancelled: Arc<AtomicBool>) -> Result<Self, String> {
+        if !(speed > 0.0 && speed.is_finite()) {
+            return Err("Replay speed must be a positive number".to_string());
+        }
+        let speed = speed.clamp(MIN_SPEED, MAX_SPEED);
+
+        let file = File::open(path).map_err(|e| e.to_string())?;
+        let mut lines = BufReader::new(file).lines();
+
+        let header = lines
+            .next()
+            .ok_or("Recording is empty")?
+            .map_err(|e| e.to_string())?;
+        let header: Value = serde_json::from_str(&header)
+            .map_err(|e| format!("Invalid asciicast header: {}", e))?;
+        if header.get("version").and_then(Value::as_u64) != Some(2) {
+            return Err("Only asciicast v2 recordings are supported".to_string());
+        }
+
+        let dimension = |key: &str, default: u16| {
+            header
+                .get(key)
+                .and_then(Value::as_u64)
+                .map_or(default, |n| n as u16)
+        };
+
+        Ok(CastPlayer {
+            lines,
+            cols: dimension("width", 80),
+            rows: dimension("height", 24),
+            started: Instant::now(),
+            speed,
+            pending: Vec::new(),
+            cancelled,
+            resized: None,
+        })
+    }
+
+    /// Terminal size from the header, as `(cols, rows)`.
+    pub fn size(&self) -> (u16, u16) {
+        (self.cols, self.rows)
+    }
+
+    /// Calls `hook` with `(cols, rows)` for every resize in the recording.
+    pub fn on_resize(&mut self, hook: impl FnMut(u16, u16) + Send + 'static) {
+        self.resized = Some(Box::new(hook));
+    }
+
+    /// Sleeps until the event at `time` is due; false if the replay was cancelled meanwhile.
+    fn wait_until(&self, time: f64) -> io::Result<bool> {
+        let offset = Duration::try_from_secs_f64(time.max(0.0) / self.speed)
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+        let due = self
+            .started
+            .checked_add(offset)
+            .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, "Event time out of range"))?;
+        loop {
+            if self.cancelled.load(Ordering::Relaxed) {
+                return Ok(false);
+            }
+            let remaining = due.saturating_duration_since(Instant::now());
+            if remaining.is_zero() {
+                return Ok(true);
+            }
+            thread::sleep(remaining.min(CANCEL_POLL_INTERVAL));
+        }
+    }
+
+    fn resize(&mut self, size: &str) -> io::Result<()> {
+        let invalid = || io::Error::new(io::ErrorKind::InvalidData, "Invalid resize event");
+        let (cols, rows) = size.split_once('x').ok_or_else(invalid)?;
+        self.cols = cols.parse().map_err(|_| invalid())?;
+        self.rows = rows.parse().map_err(|_| invalid())?;
+        if let Some(hook) = self.resized.as_mut() {
+            hook(self.cols, self.rows);
+        }
+        Ok(())
+    }
+
+    fn next_output(&mut self) -> io::Result<Option<Vec<u8>>> {
+        loop {
+            let Some(line) = self.lines.next() else {
+                return Ok(None);
+            };
+            let line = line?;
+            if line.trim().is_empty() {
+                continue;
+            }
+
+            let (time, code, data): (f64, String, String) = serde_json::from_str(&line)
+                .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+            if code != "o" && code != "r" {
+                continue;
+            }
+            if !self.wait_until(time)? {
+                return Ok(None);
+            }
+            if code == "r" {
+                self.resize(&data)?;
+                continue;
+            }
+            return Ok(Some(data.into_bytes()));
+        }
+    }
+}
+
+impl Read for CastPlayer {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if self.pending.is_empty() {
+            match self.next_output()? {
+                Some(data) => self.pending = data,
+                None => return Ok(0),
+            }
+        }
+
+        let n = buf.len().min(self.pending.len());
+        buf[..n].copy_from_slice(&self.pending[..n]);
+        self.pending.drain(..n);
+        Ok(n)
+    }
+}
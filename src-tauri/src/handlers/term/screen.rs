@@ -0,0 +1,819 @@
+use serde::Serialize;
+use unicode_width::UnicodeWidthChar;
+use vte::{Params, Parser, Perform};
+
+const TAB_WIDTH: usize = 8;
+/// Fills the second column of a wide character; left out of snapshot text.
+const WIDE_SPACER: char = '\0';
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+#[serde(tag = "type", content = "value", rename_all = "lowercase")]
+pub enum Color {
+    #[default]
+    Default,
+    Indexed(u8),
+    Rgb(u8, u8, u8),
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+pub struct Attrs {
+    pub fg: Color,
+    pub bg: Color,
+    pub bold: bool,
+    pub dim: bool,
+    pub italic: bool,
+    pub underline: bool,
+    pub blink: bool,
+    pub inverse: bool,
+    pub hidden: bool,
+    pub strikethrough: bool,
+}
+
+impl Attrs {
+    /// Applies one SGR parameter list, e.g. `1;38;5;208`.
+    pub fn apply_sgr(&mut self, params: &[u16]) {
+        if params.is_empty() {
+            *self = Attrs::default();
+            return;
+        }
+
+        let mut i = 0;
+        while i < params.len() {
+            match params[i] {
+                0 => *self = Attrs::default(),
+                1 => self.bold = true,
+                2 => self.dim = true,
+                3 => self.italic = true,
+                4 => self.underline = true,
+                5 | 6 => self.blink = true,
+                7 => self.inverse = true,
+                8 => self.hidden = true,
+                9 => self.strikethrough = true,
+                21 => self.underline = true,
+                22 => {
+                    self.bold = false;
+                    self.dim = false;
+                }
+                23 => self.italic = false,
+                24 => self.underline = false,
+                25 => self.blink = false,
+                27 => self.inverse = false,
+                28 => self.hidden = false,
+                29 => self.strikethrough = false,
+                n @ 30..=37 => self.fg = Color::Indexed((n - 30) as u8),
+                38 => {
+                    if let Some((color, used)) = extended_color(&params[i + 1..]) {
+                        self.fg = color;
+                        i += used;
+                    }
+                }
+                39 => self.fg = Color::Default,
+                n @ 40..=47 => self.bg = Color::Indexed((n - 40) as u8),
+                48 => {
+                    if let Some((color, used)) = extended_color(&params[i + 1..]) {
+                        self.bg = color;
+                        i += used;
+                    }
+                }
+                49 => self.bg = Color::Default,
+                n @ 90..=97 => self.fg = Color::Indexed((n - 90 + 8) as u8),
+                n @ 100..=107 => self.bg = Color::Indexed((n - 100 + 8) as u8),
+                _ => {}
+            }
+            i += 1;
+        }
+    }
+}
+
+/// Parses the tail of `38;5;n` / `38;2;r;g;b`, returning the color and how many params it used.
+fn extended_color(params: &[u16]) -> Option<(Color, usize)> {
+    match params {
+        [5, index, ..] => Some((Color::Indexed(*index as u8), 2)),
+        [2, r, g, b, ..] => Some((Color::Rgb(*r as u8, *g as u8, *b as u8), 4)),
+        _ => None,
+    }
+}
+
+/// Flattens vte params so both `38;5;1` and the colon form `38:5:1` read the same.
+pub fn flatten_params(params: &Params) -> Vec<u16> {
+    params.iter().flatten().copied().collect()
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Cell {
+    ch: char,
+    attrs: Attrs,
+}
+
+impl Default for Cell {
+    fn default() -> Self {
+        Cell {
+            ch: ' ',
+            attrs: Attrs::default(),
+        }
+    }
+}
+
+#[derive(Clone)]
+struct Grid {
+    rows: usize,
+    cols: usize,
+    lines: Vec<Vec<Cell>>,
+}
+
+impl Grid {
+    fn new(rows: usize, cols: usize) -> Self {
+        Grid {
+            rows,
+            cols,
+            lines: vec![vec![Cell::default(); cols]; rows],
+        }
+    }
+
+    fn resize(&mut self, rows: usize, cols: usize) {
+        // Keep the bottom of the screen, where the prompt usually is.
+        if rows < self.rows {
+            self.lines.drain(..self.rows - rows);
+        }
+        for line in &mut self.lines {
+            line.resize(cols, Cell::default());
+        }
+        while self.lines.len() < rows {
+            self.lines.push(vec![Cell::default(); cols]);
+        }
+        self.rows = rows;
+        self.cols = cols;
+    }
+
+    /// Scrolls lines `top..=bottom` up by `count`, filling from the bottom.
+    fn scroll_up(&mut self, top: usize, bottom: usize, count: usize, attrs: Attrs) {
+        let count = count.min(bottom + 1 - top);
+        self.lines[top..=bottom].rotate_left(count);
+        for line in &mut self.lines[bottom + 1 - count..=bottom] {
+            line.fill(blank(attrs));
+        }
+    }
+
+    fn scroll_down(&mut self, top: usize, bottom: usize, count: usize, attrs: Attrs) {
+        let count = count.min(bottom + 1 - top);
+        self.lines[top..=bottom].rotate_right(count);
+        for line in &mut self.lines[top..top + count] {
+            line.fill(blank(attrs));
+        }
+    }
+}
+
+/// Erased cells keep the current background, like xterm does.
+fn blank(attrs: Attrs) -> Cell {
+    Cell {
+        ch: ' ',
+        attrs: Attrs {
+            bg: attrs.bg,
+            ..Attrs::default()
+        },
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+struct Cursor {
+    row: usize,
+    col: usize,
+    attrs: Attrs,
+}
+
+impl Cursor {
+    /// Follows the text when a resize drops `dropped` lines off the top.
+    fn clamp(&mut self, dropped: usize, rows: usize, cols: usize) {
+        self.row = self.row.saturating_sub(dropped).min(rows - 1);
+        self.col = self.col.min(cols - 1);
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct StyledSpan {
+    pub start: usize,
+    pub len: usize,
+    pub attrs: Attrs,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ScreenLine {
+    pub text: String,
+    /// Runs of non-default attributes, in columns.
+    pub spans: Vec<StyledSpan>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ScreenSnapshot {
+    pub rows: usize,
+    pub cols: usize,
+    pub cursor_row: usize,
+    pub cursor_col: usize,
+    pub cursor_visible: bool,
+    pub alternate_screen: bool,
+    pub title: String,
+    pub lines: Vec<ScreenLine>,
+}
+
+struct ScreenState {
+    primary: Grid,
+    alternate: Grid,
+    alternate_active: bool,
+    cursor: Cursor,
+    saved_cursor: Cursor,
+    /// Cursor saved by `?1049h`, restored when leaving the alternate screen.
+    alternate_saved_cursor: Cursor,
+    /// Set after printing into the last column; the next glyph wraps first.
+    wrap_pending: bool,
+    autowrap: bool,
+    cursor_visible: bool,
+    origin_mode: bool,
+    scroll_top: usize,
+    scroll_bottom: usize,
+    title: String,
+}
+
+impl ScreenState {
+    fn new(rows: usize, cols: usize) -> Self {
+        ScreenState {
+            primary: Grid::new(rows, cols),
+            alternate: Grid::new(rows, cols),
+            alternate_active: false,
+            cursor: Cursor::default(),
+            saved_cursor: Cursor::default(),
+            alternate_saved_cursor: Cursor::default(),
+            wrap_pending: false,
+            autowrap: true,
+            cursor_visible: true,
+            origin_mode: false,
+            scroll_top: 0,
+            scroll_bottom: rows - 1,
+            title: String::new(),
+        }
+    }
+
+    fn grid(&mut self) -> &mut Grid {
+        if self.alternate_active {
+            &mut self.alternate
+        } else {
+            &mut self.primary
+        }
+    }
+
+    fn active(&self) -> &Grid {
+        if self.alternate_active {
+            &self.alternate
+        } else {
+            &self.primary
+        }
+    }
+
+    fn rows(&self) -> usize {
+        self.primary.rows
+    }
+
+    fn cols(&self) -> usize {
+        self.primary.cols
+    }
+
+    fn resize(&mut self, rows: usize, cols: usize) {
+        let dropped = self.rows().saturating_sub(rows);
+        self.primary.resize(rows, cols);
+        self.alternate.resize(rows, cols);
+        self.scroll_top = 0;
+        self.scroll_bottom = rows - 1;
+        self.cursor.clamp(dropped, rows, cols);
+        self.saved_cursor.clamp(dropped, rows, cols);
+        self.alternate_saved_cursor.clamp(dropped, rows, cols);
+        self.wrap_pending = false;
+    }
+
+    fn move_to(&mut self, row: usize, col: usize) {
+        let (min_row, max_row) = if self.origin_mode {
+            (self.scroll_top, self.scroll_bottom)
+        } else {
+            (0, self.rows() - 1)
+        };
+        self.cursor.row = row.clamp(min_row, max_row);
+        self.cursor.col = col.min(self.cols() - 1);
+        self.wrap_pending = false;
+    }
+
+    /// Goes back to a saved cursor, keeping it on the screen as it is now.
+    fn restore_cursor(&mut self, saved: Cursor) {
+        self.cursor = saved;
+        self.move_to(saved.row, saved.col);
+    }
+
+    fn line_feed(&mut self) {
+        if self.cursor.row == self.scroll_bottom {
+            let (top, bottom, attrs) = (self.scroll_top, self.scroll_bottom, self.cursor.attrs);
+            self.grid().scroll_up(top, bottom, 1, attrs);
+        } else if self.cursor.row + 1 < self.rows() {
+            self.cursor.row += 1;
+        }
+        self.wrap_pending = false;
+    }
+
+    fn reverse_index(&mut self) {
+        if self.cursor.row == self.scroll_top {
+            let (top, bottom, attrs) = (self.scroll_top, self.scroll_bottom, self.cursor.attrs);
+            self.grid().scroll_down(top, bottom, 1, attrs);
+        } else if self.cursor.row > 0 {
+            self.cursor.row -= 1;
+        }
+        self.wrap_pending = false;
+    }
+
+    fn erase_in_display(&mut self, mode: u16) {
+        let Cursor { row, col, attrs } = self.cursor;
+        let grid = self.grid();
+        match mode {
+            0 => {
+                grid.lines[row][col..].fill(blank(attrs));
+                for line in &mut grid.lines[row + 1..] {
+                    line.fill(blank(attrs));
+                }
+            }
+            1 => {
+                for line in &mut grid.lines[..row] {
+                    line.fill(blank(attrs));
+                }
+                grid.lines[row][..=col].fill(blank(attrs));
+            }
+            2 | 3 => {
+                for line in &mut grid.lines {
+                    line.fill(blank(attrs));
+                }
+            }
+            _ => {}
+        }
+    }
+
+    fn erase_in_line(&mut self, mode: u16) {
+        let Cursor { row, col, attrs } = self.cursor;
+        let line = &mut self.grid().lines[row];
+        match mode {
+            0 => line[col..].fill(blank(attrs)),
+            1 => line[..=col].fill(blank(attrs)),
+            2 => line.fill(blank(attrs)),
+            _ => {}
+        }
+    }
+
+    fn set_alternate_screen(&mut self, enabled: bool, save_cursor: bool) {
+        if enabled == self.alternate_active {
+            return;
+        }
+        if enabled {
+            if save_cursor {
+                self.alternate_saved_cursor = self.cursor;
+            }
+            let blank_grid = Grid::new(self.rows(), self.cols());
+            self.alternate = blank_grid;
+            self.alternate_active = true;
+        } else {
+            self.alternate_active = false;
+            if save_cursor {
+                self.restore_cursor(self.alternate_saved_cursor);
+            }
+        }
+        self.wrap_pending = false;
+    }
+
+    fn set_private_mode(&mut self, mode: u16, enabled: bool) {
+        match mode {
+            6 => {
+                self.origin_mode = enabled;
+                self.move_to(if enabled { self.scroll_top } else { 0 }, 0);
+            }
+            7 => self.autowrap = enabled,
+            25 => self.cursor_visible = enabled,
+            47 | 1047 => self.set_alternate_screen(enabled, false),
+            1048 => {
+                if enabled {
+                    self.saved_cursor = self.cursor;
+                } else {
+                    self.restore_cursor(self.saved_cursor);
+                }
+            }
+            1049 => self.set_alternate_screen(enabled, true),
+            _ => {}
+        }
+    }
+
+    fn reset(&mut self) {
+        *self = ScreenState::new(self.rows(), self.cols());
+    }
+
+    fn snapshot(&self) -> ScreenSnapshot {
+        let grid = self.active();
+        let lines = grid
+            .lines
+            .iter()
+            .map(|cells| {
+                let text = cells
+                    .iter()
+                    .map(|cell| cell.ch)
+                    .filter(|&ch| ch != WIDE_SPACER)
+                    .collect::<String>();
+                let mut spans: Vec<StyledSpan> = Vec::new();
+                for (col, cell) in cells.iter().enumerate() {
+                    if cell.attrs == Attrs::default() {
+                        continue;
+                    }
+                    match spans.last_mut() {
+                        Some(span) if span.start + span.len == col && span.attrs == cell.attrs => {
+                            span.len += 1
+                        }
+                        _ => spans.push(StyledSpan {
+                            start: col,
+                            len: 1,
+                            attrs: cell.attrs,
+                        }),
+                    }
+                }
+                ScreenLine {
+                    text: text.trim_end().to_string(),
+                    spans,
+                }
+            })
+            .collect();
+
+        ScreenSnapshot {
+            rows: grid.rows,
+            cols: grid.cols,
+            cursor_row: self.cursor.row,
+            cursor_col: self.cursor.col,
+            cursor_visible: self.cursor_visible,
+            alternate_screen: self.alternate_active,
+            title: self.title.clone(),
+            lines,
+        }
+    }
+}
+
+impl Perform for ScreenState {
+    fn print(&mut self, c: char) {
+        // Combining marks have no cell of their own and are dropped.
+        let width = c.width().unwrap_or(1).min(self.cols());
+        if width == 0 {
+            return;
+        }
+
+        if self.wrap_pending {
+            if self.autowrap {
+                self.cursor.col = 0;
+                self.line_feed();
+            }
+            self.wrap_pending = false;
+        }
+
+        // A wide character that does not fit in the last column goes to the
+        // next line, or without autowrap onto the last two columns.
+        if self.cursor.col + width > self.cols() {
+            if self.autowrap {
+                self.cursor.col = 0;
+                self.line_feed();
+            } else {
+                self.cursor.col = self.cols() - width;
+            }
+        }
+
+        let Cursor { row, col, attrs } = self.cursor;
+        let cols = self.cols();
+        let line = &mut self.grid().lines[row];
+        // Overwriting either half of a wide character erases the other half.
+        if col > 0 && line[col].ch == WIDE_SPACER {
+            line[col - 1].ch = ' ';
+        }
+        if col + width < cols && line[col + width].ch == WIDE_SPACER {
+            line[col + width].ch = ' ';
+        }
+        line[col] = Cell { ch: c, attrs };
+        if width == 2 {
+            line[col + 1] = Cell {
+                ch: WIDE_SPACER,
+                attrs,
+            };
+        }
+
+        if col + width < cols {
+            self.cursor.col += width;
+        } else {
+            self.cursor.col = cols - 1;
+            self.wrap_pending = true;
+        }
+    }
+
+    fn execute(&mut self, byte: u8) {
+        match byte {
+            b'\n' | 0x0b | 0x0c => self.line_feed(),
+            b'\r' => {
+                self.cursor.col = 0;
+                self.wrap_pending = false;
+            }
+            0x08 => {
+                self.cursor.col = self.cursor.col.saturating_sub(1);
+                self.wrap_pending = false;
+            }
+            b'\t' => {
+                let next = (self.cursor.col / TAB_WIDTH + 1) * TAB_WIDTH;
+                self.cursor.col = next.min(self.cols() - 1);
+            }
+            _ => {}
+        }
+    }
+
+    fn csi_dispatch(&mut self, params: &Params, intermediates: &[u8], ignore: bool, action: char) {
+        if ignore {
+            return;
+        }
+
+        let params = flatten_params(params);
+        // Missing or zero parameters mean "1" for movement sequences.
+        let count = |i: usize| params.get(i).copied().filter(|&n| n > 0).unwrap_or(1) as usize;
+        let Cursor { row, col, .. } = self.cursor;
+
+        match (intermediates, action) {
+            ([], 'A') => {
+                // Vertical moves stop at the scroll region when they start inside it.
+                let top = if row >= self.scroll_top {
+                    self.scroll_top
+                } else {
+                    0
+                };
+                self.move_to(row.saturating_sub(count(0)).max(top), col)
+            }
+            ([], 'B') | ([], 'e') => {
+                let bottom = if row <= self.scroll_bottom {
+                    self.scroll_bottom
+                } else {
+                    self.rows() - 1
+                };
+                self.move_to((row + count(0)).min(bottom), col)
+            }
+            ([], 'C') | ([], 'a') => self.move_to(row, col + count(0)),
+            ([], 'D') => self.move_to(row, col.saturating_sub(count(0))),
+            ([], 'E') => self.move_to(row + count(0), 0),
+            ([], 'F') => self.move_to(row.saturating_sub(count(0)), 0),
+            ([], 'G') | ([], '`') => self.move_to(row, count(0) - 1),
+            ([], 'd') => {
+                let offset = if self.origin_mode { self.scroll_top } else { 0 };
+                self.move_to(offset + count(0) - 1, col)
+            }
+            ([], 'H') | ([], 'f') => {
+                let offset = if self.origin_mode { self.scroll_top } else { 0 };
+                self.move_to(offset + count(0) - 1, count(1) - 1)
+            }
+            ([], 'J') => self.erase_in_display(params.first().copied().unwrap_or(0)),
+            ([], 'K') => self.erase_in_line(params.first().copied().unwrap_or(0)),
+            ([], 'L') | ([], 'M') if (self.scroll_top..=self.scroll_bottom).contains(&row) => {
+                let (bottom, attrs) = (self.scroll_bottom, self.cursor.attrs);
+                if action == 'L' {
+                    self.grid().scroll_down(row, bottom, count(0), attrs);
+                } else {
+                    self.grid().scroll_up(row, bottom, count(0), attrs);
+                }
+                self.cursor.col = 0;
+            }
+            ([], 'P') => {
+                let attrs = self.cursor.attrs;
+                let line = &mut self.grid().lines[row];
+                let n = count(0).min(line.len() - col);
+                line[col..].rotate_left(n);
+                let len = line.len();
+                line[len - n..].fill(blank(attrs));
+            }
+            ([], '@') => {
+                let attrs = self.cursor.attrs;
+                let line = &mut self.grid().lines[row];
+                let n = count(0).min(line.len() - col);
+                line[col..].rotate_right(n);
+                line[col..col + n].fill(blank(attrs));
+            }
+            ([], 'X') => {
+                let attrs = self.cursor.attrs;
+                let line = &mut self.grid().lines[row];
+                let end = (col + count(0)).min(line.len());
+                line[col..end].fill(blank(attrs));
+            }
+            ([], 'S') => {
+                let (top, bottom, attrs) = (self.scroll_top, self.scroll_bottom, self.cursor.attrs);
+                self.grid().scroll_up(top, bottom, count(0), attrs);
+            }
+            ([], 'T') => {
+                let (top, bottom, attrs) = (self.scroll_top, self.scroll_bottom, self.cursor.attrs);
+                self.grid().scroll_down(top, bottom, count(0), attrs);
+            }
+            ([], 'm') => self.cursor.attrs.apply_sgr(&params),
+            ([], 'r') => {
+                let top = count(0) - 1;
+                let bottom = params
+                    .get(1)
+                    .copied()
+                    .filter(|&n| n > 0)
+                    .map_or(self.rows(), |n| n as usize)
+                    .min(self.rows())
+                    - 1;
+                if top < bottom {
+                    self.scroll_top = top;
+                    self.scroll_bottom = bottom;
+                    self.move_to(if self.origin_mode { top } else { 0 }, 0);
+                }
+            }
+            ([], 's') => self.saved_cursor = self.cursor,
+            ([], 'u') => self.restore_cursor(self.saved_cursor),
+            ([b'?'], 'h') | ([b'?'], 'l') => {
+                for &mode in &params {
+                    self.set_private_mode(mode, action == 'h');
+                }
+            }
+            _ => {}
+        }
+    }
+
+    fn esc_dispatch(&mut self, intermediates: &[u8], _ignore: bool, byte: u8) {
+        match (intermediates, byte) {
+            ([], b'7') => self.saved_cursor = self.cursor,
+            ([], b'8') => self.restore_cursor(self.saved_cursor),
+            ([], b'D') => self.line_feed(),
+            ([], b'E') => {
+                self.cursor.col = 0;
+                self.line_feed();
+            }
+            ([], b'M') => self.reverse_index(),
+            ([], b'c') => self.reset(),
+            _ => {}
+        }
+    }
+
+    fn osc_dispatch(&mut self, params: &[&[u8]], _bell_terminated: bool) {
+        if let [kind, title, ..] = params {
+            if *kind == b"0" || *kind == b"2" {
+                self.title = String::from_utf8_lossy(title).into_owned();
+            }
+        }
+    }
+}
+
+/// Headless VT100/xterm screen fed with the same bytes as the webview terminal.
+pub struct Screen {
+    parser: Parser,
+    state: ScreenState,
+}
+
+impl Screen {
+    pub fn new(rows: u16, cols: u16) -> Self {
+        Screen {
+            parser: Parser::new(),
+            state: ScreenState::new(rows.max(1) as usize, cols.max(1) as usize),
+        }
+    }
+
+    pub fn feed(&mut self, bytes: &[u8]) {
+        self.parser.advance(&mut self.state, bytes);
+    }
+
+    pub fn resize(&mut self, rows: u16, cols: u16) {
+        self.state
+            .resize(rows.max(1) as usize, cols.max(1) as usize);
+    }
+
+    pub fn snapshot(&self) -> ScreenSnapshot {
+        self.state.snapshot()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn feed(rows: u16, cols: u16, bytes: &[u8]) -> Screen {
+        let mut screen = Screen::new(rows, cols);
+        screen.feed(bytes);
+        screen
+    }
+
+    fn text(screen: &Screen) -> Vec<String> {
+        screen
+            .snapshot()
+            .lines
+            .into_iter()
+            .map(|line| line.text)
+            .collect()
+    }
+
+    fn cursor(screen: &Screen) -> (usize, usize) {
+        let snapshot = screen.snapshot();
+        (snapshot.cursor_row, snapshot.cursor_col)
+    }
+
+    #[test]
+    fn sgr_sets_and_resets_attributes() {
+        let screen = feed(
+            2,
+            10,
+            b"\x1b[1;31mA\x1b[38;5;208mB\x1b[48;2;1;2;3mC\x1b[0mD\x1b[38:5:9mE",
+        );
+        let line = &screen.snapshot().lines[0];
+        assert_eq!(line.text, "ABCDE");
+
+        let attrs: Vec<(usize, Attrs)> = line.spans.iter().map(|s| (s.start, s.attrs)).collect();
+        assert_eq!(attrs.len(), 4);
+        assert_eq!(attrs[0].1.fg, Color::Indexed(1));
+        assert!(attrs[0].1.bold);
+        assert_eq!(attrs[1].1.fg, Color::Indexed(208));
+        assert_eq!(attrs[2].1.bg, Color::Rgb(1, 2, 3));
+        assert_eq!(attrs[2].1.fg, Color::Indexed(208));
+        // `D` has default attributes, so the colon form starts a new span at column 4.
+        assert_eq!(attrs[3].0, 4);
+        assert_eq!(attrs[3].1.fg, Color::Indexed(9));
+        assert!(!attrs[3].1.bold);
+    }
+
+    #[test]
+    fn line_feed_scrolls_only_the_scroll_region() {
+        let screen = feed(
+            5,
+            10,
+            b"top\x1b[2;4r\x1b[5;1Hbottom\x1b[2;1Ha\r\nb\r\nc\r\nd",
+        );
+        assert_eq!(text(&screen), ["top", "b", "c", "d", "bottom"]);
+        assert_eq!(cursor(&screen), (3, 1));
+    }
+
+    #[test]
+    fn insert_and_delete_lines_stay_inside_the_scroll_region() {
+        let screen = feed(4, 10, b"1\r\n2\r\n3\r\n4\x1b[1;3r\x1b[1;1H\x1b[L");
+        assert_eq!(text(&screen), ["", "1", "2", "4"]);
+    }
+
+    #[test]
+    fn alternate_screen_restores_primary_contents_and_cursor() {
+        let mut screen = feed(3, 10, b"shell$ vim\x1b[?1049h\x1b[2J\x1b[1;1Hediting");
+        assert!(screen.snapshot().alternate_screen);
+        assert_eq!(text(&screen), ["editing", "", ""]);
+
+        screen.feed(b"\x1b[?1049l");
+        let snapshot = screen.snapshot();
+        assert!(!snapshot.alternate_screen);
+        assert_eq!(text(&screen), ["shell$ vim", "", ""]);
+        assert_eq!(cursor(&screen), (0, 10 - 1));
+    }
+
+    #[test]
+    fn resize_keeps_the_bottom_lines_and_the_cursor_on_them() {
+        let mut screen = feed(4, 10, b"1\r\n2\r\n3\r\n4");
+        screen.resize(2, 5);
+        assert_eq!(text(&screen), ["3", "4"]);
+        assert_eq!(cursor(&screen), (1, 1));
+
+        screen.resize(3, 5);
+        assert_eq!(text(&screen), ["3", "4", ""]);
+        screen.feed(b"\r\nx");
+        assert_eq!(text(&screen), ["3", "4", "x"]);
+    }
+
+    #[test]
+    fn wide_characters_take_two_columns_and_wrap_at_the_right_margin() {
+        let mut screen = feed(3, 5, "界x".as_bytes());
+        assert_eq!(cursor(&screen), (0, 3));
+
+        // Two columns left: the wide character fits and the next one wraps.
+        screen.feed("\r\nabc界y".as_bytes());
+        assert_eq!(text(&screen), ["界x", "abc界", "y"]);
+
+        // One column left: the wide character moves to the next line.
+        let screen = feed(3, 5, "abcd界".as_bytes());
+        assert_eq!(text(&screen), ["abcd", "界", ""]);
+        assert_eq!(cursor(&screen), (1, 2));
+
+        // Without autowrap it lands on the last two columns.
+        let screen = feed(3, 5, "\x1b[?7labcd界".as_bytes());
+        assert_eq!(text(&screen), ["abc界", "", ""]);
+    }
+
+    #[test]
+    fn saved_cursors_are_clamped_after_shrinking() {
+        // Leaving vim after the window got smaller.
+        let mut screen = feed(40, 80, b"\x1b[35;70H\x1b[?1049h");
+        screen.resize(10, 20);
+        screen.feed(b"\x1b[?1049lx");
+        // The 30 lines dropped off the top take the saved row from 34 to 4.
+        assert_eq!(text(&screen)[4], format!("{}x", " ".repeat(19)));
+
+        for (save, restore) in [
+            (&b"\x1b[?1048h"[..], &b"\x1b[?1048l"[..]),
+            (b"\x1b7", b"\x1b8"),
+            (b"\x1b[s", b"\x1b[u"),
+        ] {
+            let mut screen = feed(40, 80, b"\x1b[35;70H");
+            screen.feed(save);
+            screen.resize(10, 20);
+            screen.feed(restore);
+            screen.feed(b"x");
+            assert_eq!(cursor(&screen), (4, 19));
+        }
+    }
+}
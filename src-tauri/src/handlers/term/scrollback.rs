@@ -0,0 +1,117 @@
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+
+/// Upper bound for a single session's scrollback, whatever the configured limit.
+const MAX_SCROLLBACK_BYTES: usize = 16 * 1024 * 1024;
+const DEFAULT_SCROLLBACK_LINES: usize = 10_000;
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ScrollbackLimit {
+    Lines(usize),
+    Bytes(usize),
+}
+
+impl Default for ScrollbackLimit {
+    fn default() -> Self {
+        ScrollbackLimit::Lines(DEFAULT_SCROLLBACK_LINES)
+    }
+}
+
+/// Raw PTY output kept per line, oldest lines dropped first.
+pub struct Scrollback {
+    lines: VecDeque<Vec<u8>>,
+    bytes: usize,
+    max_lines: usize,
+    max_bytes: usize,
+    /// Total number of bytes ever pushed, used to line replays up with live output.
+    offset: u64,
+    /// Lines trimmed so far, so line numbers stay stable while output streams in.
+    dropped_lines: u64,
+}
+
+impl Scrollback {
+    pub fn new(limit: ScrollbackLimit) -> Self {
+        let (max_lines, max_bytes) = match limit {
+            ScrollbackLimit::Lines(lines) => (lines.max(1), MAX_SCROLLBACK_BYTES),
+            ScrollbackLimit::Bytes(bytes) => (usize::MAX, bytes.clamp(1, MAX_SCROLLBACK_BYTES)),
+        };
+
+        Scrollback {
+            lines: VecDeque::new(),
+            bytes: 0,
+            max_lines,
+            max_bytes,
+            offset: 0,
+            dropped_lines: 0,
+        }
+    }
+
+    /// Appends output and returns the stream offset just past it.
+    pub fn push(&mut self, mut data: &[u8]) -> u64 {
+        self.offset += data.len() as u64;
+        self.bytes += data.len();
+
+        while !data.is_empty() {
+            let line_end = data
+                .iter()
+                .position(|&b| b == b'\n')
+                .map_or(data.len(), |i| i + 1);
+            let (line, rest) = data.split_at(line_end);
+
+            match self.lines.back_mut() {
+                Some(last) if last.last() != Some(&b'\n') => last.extend_from_slice(line),
+                _ => self.lines.push_back(line.to_vec()),
+            }
+            data = rest;
+        }
+
+        self.trim();
+        self.offset
+    }
+
+    fn trim(&mut self) {
+        while self.lines.len() > self.max_lines {
+            if let Some(line) = self.lines.pop_front() {
+                self.bytes -= line.len();
+                self.dropped_lines += 1;
+            }
+        }
+
+        while self.bytes > self.max_bytes {
+            let excess = self.bytes - self.max_bytes;
+            let Some(front) = self.lines.front_mut() else {
+                break;
+            };
+            if front.len() <= excess {
+                self.bytes -= front.len();
+                self.lines.pop_front();
+                self.dropped_lines += 1;
+            } else {
+                front.drain(..excess);
+                self.bytes -= excess;
+            }
+        }
+    }
+
+    pub fn offset(&self) -> u64 {
+        self.offset
+    }
+
+    /// Absolute number of the oldest line still kept.
+    pub fn first_line(&self) -> u64 {
+        self.dropped_lines
+    }
+
+    pub fn lines(&self) -> impl Iterator<Item = &[u8]> {
+        self.lines.iter().map(Vec::as_slice)
+    }
+
+    pub fn contents(&self) -> Vec<u8> {
+        let mut contents = Vec::with_capacity(self.bytes);
+        for line in &self.lines {
+            contents.extend_from_slice(line);
+        }
+        contents
+    }
+}
@@ -0,0 +1,65 @@
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+
+use super::ansi::strip_line;
+
+#[derive(Debug, Clone, Copy, Default, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SearchDirection {
+    #[default]
+    Forward,
+    Backward,
+}
+
+/// A match in scrollback; `column` and `length` count characters of the ANSI-stripped line.
+#[derive(Debug, Serialize)]
+pub struct SearchMatch {
+    pub line: u64,
+    pub column: usize,
+    pub length: usize,
+}
+
+/// Searches `lines` (numbered from `first_line`) starting at `start_line`,
+/// inclusive, in the given direction.
+pub fn search_lines(
+    lines: &[Vec<u8>],
+    first_line: u64,
+    regex: &Regex,
+    direction: SearchDirection,
+    start_line: Option<u64>,
+    max_results: usize,
+) -> Vec<SearchMatch> {
+    let start = start_line.map(|line| line.saturating_sub(first_line) as usize);
+    let indices: Box<dyn Iterator<Item = usize>> = match direction {
+        SearchDirection::Forward => Box::new(start.unwrap_or(0)..lines.len()),
+        SearchDirection::Backward => {
+            let end = start.map_or(lines.len(), |start| (start + 1).min(lines.len()));
+            Box::new((0..end).rev())
+        }
+    };
+
+    let mut matches = Vec::new();
+    for index in indices {
+        let text = strip_line(&lines[index]);
+        let mut line_matches: Vec<SearchMatch> = regex
+            .find_iter(&text)
+            .filter(|m| !m.is_empty())
+            .map(|m| SearchMatch {
+                line: first_line + index as u64,
+                column: text[..m.start()].chars().count(),
+                length: m.as_str().chars().count(),
+            })
+            .collect();
+
+        if let SearchDirection::Backward = direction {
+            line_matches.reverse();
+        }
+        for found in line_matches {
+            if matches.len() == max_results {
+                return matches;
+            }
+            matches.push(found);
+        }
+    }
+    matches
+}
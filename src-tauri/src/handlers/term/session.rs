@@ -0,0 +1,429 @@
+use portable_pty::{
+    native_pty_system, Child, ChildKiller, CommandBuilder, ExitStatus, MasterPty, PtySize,
+};
+use serde::Serialize;
+use std::{
+    collections::{BTreeSet, HashMap},
+    io::Write,
+    path::Path,
+    sync::{
+        atomic::{AtomicBool, AtomicU32, Ordering},
+        mpsc::Receiver,
+        Arc, Mutex as StdMutex,
+    },
+    thread,
+    time::Duration,
+};
+use tauri::{async_runtime::Mutex as AsyncMutex, AppHandle, Emitter};
+
+#[cfg(unix)]
+use super::daemon;
+use super::{
+    decoder::OutputEncoding,
+    recording::CastPlayer,
+    scrollback::ScrollbackLimit,
+    status::{spawn_status_poller, SessionStatus},
+    stream::{spawn_output_pump, SessionOutput},
+    triggers::TriggerEngine,
+};
+#[cfg(unix)]
+use crate::handlers::config::triggers::read_trigger_rules;
+use crate::handlers::config::triggers::TriggerRule;
+
+pub type SessionId = u32;
+
+/// How long the exit watcher waits for the last output to reach the webview
+/// before announcing that the session is gone.
+const DRAIN_TIMEOUT: Duration = Duration::from_millis(500);
+
+type SessionMap = Arc<AsyncMutex<HashMap<SessionId, Arc<PtySession>>>>;
+type ReplayMap = Arc<AsyncMutex<HashMap<SessionId, Arc<ReplaySession>>>>;
+
+pub struct PtySession {
+    pub master: AsyncMutex<Box<dyn MasterPty + Send>>,
+    pub writer: AsyncMutex<Box<dyn Write + Send>>,
+    pub killer: AsyncMutex<Box<dyn ChildKiller + Send + Sync>>,
+    pub output: Arc<SessionOutput>,
+    /// PID of the shell the session was started with.
+    pub pid: Option<u32>,
+    /// Last foreground process reported by the status poller.
+    pub status: StdMutex<Option<SessionStatus>>,
+    /// Why the shell runs in-process rather than in the session daemon, in
+    /// which case it ends with the app.
+    pub local_reason: Option<String>,
+}
+
+/// A recording played back through the regular output path; it has no PTY to write to.
+pub struct ReplaySession {
+    pub output: Arc<SessionOutput>,
+    cancelled: Arc<AtomicBool>,
+}
+
+#[derive(Serialize, Clone)]
+struct SessionExited {
+    session_id: SessionId,
+    exit_code: u32,
+    signal: Option<String>,
+}
+
+#[derive(Serialize, Clone)]
+struct SessionResized {
+    session_id: SessionId,
+    cols: u16,
+    rows: u16,
+}
+
+#[derive(Serialize, Clone)]
+struct BroadcastChanged {
+    session_ids: Vec<SessionId>,
+}
+
+/// Owns every open PTY; each terminal widget talks to its own session by ID.
+pub struct SessionManager {
+    sessions: SessionMap,
+    replays: ReplayMap,
+    /// Sessions whose input is mirrored to each other. Ordered, so writers are
+    /// always locked in the same order.
+    broadcast: AsyncMutex<BTreeSet<SessionId>>,
+    next_id: AtomicU32,
+}
+
+impl SessionManager {
+    pub fn new() -> Self {
+        SessionManager {
+            sessions: Arc::new(AsyncMutex::new(HashMap::new())),
+            replays: Arc::new(AsyncMutex::new(HashMap::new())),
+            broadcast: AsyncMutex::new(BTreeSet::new()),
+            next_id: AtomicU32::new(1),
+        }
+    }
+
+    /// Opens a fresh PTY, spawns `cmd` on its slave side, registers the session
+    /// and starts streaming its output to the webview. The session removes
+    /// itself and emits `session_exited` once the child is gone.
+    pub async fn create(
+        &self,
+        app: &AppHandle,
+        cmd: CommandBuilder,
+        size: PtySize,
+        encoding: OutputEncoding,
+        scrollback: ScrollbackLimit,
+        triggers: TriggerEngine,
+    ) -> Result<SessionId, String> {
+        let id = self.next_id.fetch_add(1, Ordering::Relaxed);
+        #[cfg(unix)]
+        let profile = triggers.profile().unwrap_or_default().to_string();
+        let output = SessionOutput::new(encoding, scrollback, size.rows, size.cols, triggers);
+        // Shells live in the session daemon when it is available, so they
+        // survive the app; otherwise the PTY is opened in-process.
+        #[cfg(unix)]
+        let (master, child, local_reason) =
+            match daemon::client::spawn(id, &profile, encoding, scrollback, &cmd, size) {
+                Ok(pty) => (pty.master, pty.child, None),
+                Err(err) => {
+                    let (master, child) = open_local(cmd, size)?;
+                    (master, child, Some(err.to_string()))
+                }
+            };
+        #[cfg(not(unix))]
+        let (master, child, local_reason) = {
+            let (master, child) = open_local(cmd, size)?;
+            let reason = "The session daemon is only available on Unix".to_string();
+            (master, child, Some(reason))
+        };
+
+        self.start(app, id, master, child, output, local_reason)
+            .await?;
+        Ok(id)
+    }
+
+    /// Reattaches the shells a previous run left in the session daemon, with
+    /// their kept output restored as scrollback. Shells another running
+    /// instance is attached to are left to it.
+    #[cfg(unix)]
+    pub async fn restore(&self, app: &AppHandle) {
+        let rules = read_trigger_rules(app).unwrap_or_default();
+        for kept in daemon::client::list() {
+            // Including sessions that stay with another window, whose IDs
+            // the daemon would refuse for new shells.
+            self.next_id.fetch_max(kept.id + 1, Ordering::Relaxed);
+            let Ok((pty, history)) = daemon::client::attach(&kept) else {
+                continue;
+            };
+
+            let output = SessionOutput::new(
+                kept.encoding,
+                kept.scrollback,
+                kept.size.rows,
+                kept.size.cols,
+                TriggerEngine::new(&kept.profile, &rules),
+            );
+            output.restore(&history);
+            let _ = self
+                .start(app, kept.id, pty.master, pty.child, output, None)
+                .await;
+        }
+    }
+
+    /// Registers a session around a spawned child and starts streaming its output.
+    async fn start(
+        &self,
+        app: &AppHandle,
+        id: SessionId,
+        master: Box<dyn MasterPty + Send>,
+        child: Box<dyn Child + Send + Sync>,
+        output: SessionOutput,
+        local_reason: Option<String>,
+    ) -> Result<(), String> {
+        let reader = master.try_clone_reader().map_err(|err| err.to_string())?;
+        let writer = master.take_writer().map_err(|err| err.to_string())?;
+
+        let session = PtySession {
+            master: AsyncMutex::new(master),
+            writer: AsyncMutex::new(writer),
+            killer: AsyncMutex::new(child.clone_killer()),
+            output: Arc::new(output),
+            pid: child.process_id(),
+            status: StdMutex::new(None),
+            local_reason,
+        };
+
+        let output = session.output.clone();
+        let session = Arc::new(session);
+        self.sessions.lock().await.insert(id, session.clone());
+        let drained = spawn_output_pump(app.clone(), id, reader, output);
+        spawn_status_poller(app.clone(), id, Arc::downgrade(&session));
+        spawn_exit_watcher(app.clone(), self.sessions.clone(), id, child, drained);
+        Ok(())
+    }
+
+    /// Plays a `.cast` file back as a read-only session that emits `pty_output`
+    /// like a live shell, `session_resized` where the recording was resized and
+    /// `session_exited` when it ends.
+    pub async fn replay(
+        &self,
+        app: &AppHandle,
+        path: &Path,
+        speed: f64,
+    ) -> Result<SessionId, String> {
+        let cancelled = Arc::new(AtomicBool::new(false));
+        let mut player = CastPlayer::open(path, speed, cancelled.clone())?;
+        let (cols, rows) = player.size();
+
+        let replay = ReplaySession {
+            output: Arc::new(SessionOutput::new(
+                OutputEncoding::Utf8,
+                ScrollbackLimit::default(),
+                rows,
+                cols,
+                TriggerEngine::default(),
+            )),
+            cancelled,
+        };
+
+        let output = replay.output.clone();
+        let id = self.next_id.fetch_add(1, Ordering::Relaxed);
+        player.on_resize({
+            let app = app.clone();
+            let output = output.clone();
+            move |cols, rows| {
+                output.screen.lock().unwrap().resize(rows, cols);
+                let _ = app.emit(
+                    "session_resized",
+                    SessionResized {
+                        session_id: id,
+                        cols,
+                        rows,
+                    },
+                );
+            }
+        });
+        self.replays.lock().await.insert(id, Arc::new(replay));
+        let drained = spawn_output_pump(app.clone(), id, Box::new(player), output);
+
+        let app = app.clone();
+        let replays = self.replays.clone();
+        thread::spawn(move || {
+            let _ = drained.recv();
+            replays.blocking_lock().remove(&id);
+            let _ = app.emit(
+                "session_exited",
+                SessionExited {
+                    session_id: id,
+                    exit_code: 0,
+                    signal: None,
+                },
+            );
+        });
+
+        Ok(id)
+    }
+
+    /// Kills the session's child (or stops a replay); cleanup happens in the exit watcher.
+    pub async fn kill(&self, id: SessionId) -> Result<(), String> {
+        if let Some(replay) = self.replays.lock().await.get(&id) {
+            replay.cancelled.store(true, Ordering::Relaxed);
+            return Ok(());
+        }
+
+        let session = self.get(id).await?;
+        let mut killer = session.killer.lock().await;
+        killer.kill().map_err(|err| err.to_string())
+    }
+
+    /// Makes `ids` one broadcast group, replacing the previous one; fewer than
+    /// two sessions turn broadcasting off. Emits `broadcast_changed`.
+    pub async fn set_broadcast(
+        &self,
+        app: &AppHandle,
+        ids: Vec<SessionId>,
+    ) -> Result<Vec<SessionId>, String> {
+        let sessions = self.sessions.lock().await;
+        if let Some(id) = ids.iter().find(|id| !sessions.contains_key(id)) {
+            return Err(format!("Unknown terminal session: {}", id));
+        }
+
+        let mut group = self.broadcast.lock().await;
+        *group = ids.into_iter().collect();
+        if group.len() < 2 {
+            group.clear();
+        }
+
+        let session_ids: Vec<SessionId> = group.iter().copied().collect();
+        let _ = app.emit(
+            "broadcast_changed",
+            BroadcastChanged {
+                session_ids: session_ids.clone(),
+            },
+        );
+        Ok(session_ids)
+    }
+
+    pub async fn broadcast_group(&self) -> Vec<SessionId> {
+        let sessions = self.sessions.lock().await;
+        let group = self.broadcast.lock().await;
+        group
+            .iter()
+            .copied()
+            .filter(|id| sessions.contains_key(id))
+            .collect()
+    }
+
+    /// Writes input to the session, or to its whole broadcast group if it is in one.
+    pub async fn write(&self, id: SessionId, data: &[u8]) -> Result<(), String> {
+        let targets: Vec<(SessionId, Arc<PtySession>)> = {
+            let sessions = self.sessions.lock().await;
+            let group = self.broadcast.lock().await;
+            if group.contains(&id) {
+                group
+                    .iter()
+                    .filter_map(|id| Some((*id, sessions.get(id)?.clone())))
+                    .collect()
+            } else {
+                let session = sessions
+                    .get(&id)
+                    .cloned()
+                    .ok_or_else(|| format!("Unknown terminal session: {}", id))?;
+                vec![(id, session)]
+            }
+        };
+
+        // Every writer is held before any is written to, so two broadcasts
+        // never interleave differently in different shells.
+        let mut writers = Vec::with_capacity(targets.len());
+        for (id, session) in &targets {
+            writers.push((*id, session.writer.lock().await));
+        }
+
+        let failed: Vec<String> = writers
+            .iter_mut()
+            .filter_map(|(id, writer)| {
+                let result = writer.write_all(data).and_then(|_| writer.flush());
+                result.err().map(|err| format!("session {}: {}", id, err))
+            })
+            .collect();
+        if failed.is_empty() {
+            Ok(())
+        } else {
+            Err(format!("Failed to write input to {}", failed.join(", ")))
+        }
+    }
+
+    pub async fn reload_triggers(&self, rules: &[TriggerRule]) {
+        for session in self.sessions.lock().await.values() {
+            session.output.triggers.lock().unwrap().set_rules(rules);
+        }
+    }
+
+    /// Output state of a live session or a replay.
+    pub async fn output(&self, id: SessionId) -> Result<Arc<SessionOutput>, String> {
+        if let Some(replay) = self.replays.lock().await.get(&id) {
+            return Ok(replay.output.clone());
+        }
+        Ok(self.get(id).await?.output.clone())
+    }
+
+    pub async fn get(&self, id: SessionId) -> Result<Arc<PtySession>, String> {
+        self.sessions
+            .lock()
+            .await
+            .get(&id)
+            .cloned()
+            .ok_or_else(|| format!("Unknown terminal session: {}", id))
+    }
+}
+
+fn open_local(
+    cmd: CommandBuilder,
+    size: PtySize,
+) -> Result<(Box<dyn MasterPty + Send>, Box<dyn Child + Send + Sync>), String> {
+    let pty_pair = native_pty_system()
+        .openpty(size)
+        .map_err(|err| err.to_string())?;
+
+    let child = pty_pair
+        .slave
+        .spawn_command(cmd)
+        .map_err(|err| err.to_string())?;
+    // The slave end belongs to the child now; keeping it open here would
+    // stop the reader from ever seeing EOF.
+    drop(pty_pair.slave);
+
+    Ok((pty_pair.master, child))
+}
+
+fn spawn_exit_watcher(
+    app: AppHandle,
+    sessions: SessionMap,
+    session_id: SessionId,
+    mut child: Box<dyn Child + Send + Sync>,
+    drained: Receiver<()>,
+) {
+    thread::spawn(move || {
+        let status = child
+            .wait()
+            .unwrap_or_else(|_| ExitStatus::with_exit_code(1));
+
+        // Let the pump flush what the shell printed last; it hangs up once the PTY hits EOF.
+        let _ = drained.recv_timeout(DRAIN_TIMEOUT);
+        // Dropping the session closes the master side of the PTY.
+        sessions.blocking_lock().remove(&session_id);
+
+        let _ = app.emit(
+            "session_exited",
+            SessionExited {
+                session_id,
+                exit_code: status.exit_code(),
+                signal: exit_signal(&status),
+            },
+        );
+    });
+}
+
+/// portable-pty keeps the signal name private and only exposes it through `Display`.
+pub fn exit_signal(status: &ExitStatus) -> Option<String> {
+    status
+        .to_string()
+        .strip_prefix("Terminated by ")
+        .map(str::to_string)
+}
@@ -0,0 +1,262 @@
+use portable_pty::CommandBuilder;
+use serde::Serialize;
+use std::{
+    collections::VecDeque,
+    ffi::OsString,
+    fs,
+    path::{Path, PathBuf},
+    time::{SystemTime, UNIX_EPOCH},
+};
+use tauri::{AppHandle, Manager};
+use vte::{Parser, Perform};
+
+const BASH_INTEGRATION: &str = include_str!("integration/bash.sh");
+const ZSH_INTEGRATION: &str = include_str!("integration/zsh.zsh");
+
+/// Finished commands kept per session for widgets that attach later.
+const COMMAND_HISTORY_LIMIT: usize = 500;
+const MAX_COMMAND_LENGTH: usize = 4096;
+
+#[derive(Debug, Clone, Serialize)]
+pub struct FinishedCommand {
+    pub command: String,
+    pub cwd: Option<String>,
+    pub exit_code: Option<i32>,
+    /// Unix time in milliseconds.
+    pub started_at: u64,
+    pub finished_at: u64,
+    pub duration_ms: u64,
+}
+
+#[derive(Default, PartialEq)]
+enum Phase {
+    #[default]
+    Idle,
+    /// Between `133;B` and `133;C`: whatever is printed is the command line being typed.
+    Input,
+    Running,
+}
+
+#[derive(Default)]
+struct CommandTracker {
+    phase: Phase,
+    cwd: Option<String>,
+    input: String,
+    command: String,
+    command_cwd: Option<String>,
+    started_at: u64,
+    history: VecDeque<FinishedCommand>,
+    finished: Vec<FinishedCommand>,
+}
+
+fn now_millis() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_millis() as u64)
+        .unwrap_or(0)
+}
+
+impl CommandTracker {
+    fn on_prompt_mark(&mut self, params: &[&[u8]]) {
+        match params.first().copied() {
+            Some(b"A") => self.phase = Phase::Idle,
+            Some(b"B") => {
+                self.phase = Phase::Input;
+                self.input.clear();
+            }
+            Some(b"C") => {
+                // `cmdline=` may itself contain `;`, which the OSC parser split on.
+                let cmdline = params[1..]
+                    .iter()
+                    .position(|p| p.starts_with(b"cmdline="))
+                    .map(|i| {
+                        let rest: Vec<String> = params[1 + i..]
+                            .iter()
+                            .map(|p| String::from_utf8_lossy(p).into_owned())
+                            .collect();
+                        rest.join(";")["cmdline=".len()..].to_string()
+                    });
+
+                self.command = cmdline.unwrap_or_else(|| self.input.trim().to_string());
+                self.command_cwd = self.cwd.clone();
+                self.started_at = now_millis();
+                self.phase = Phase::Running;
+            }
+            Some(b"D") => {
+                // A `D` without a `C` is an empty prompt, not a command.
+                if self.phase != Phase::Running {
+                    return;
+                }
+                let exit_code = params
+                    .get(1)
+                    .and_then(|code| std::str::from_utf8(code).ok())
+                    .and_then(|code| code.parse().ok());
+                let finished_at = now_millis();
+
+                let command = FinishedCommand {
+                    command: std::mem::take(&mut self.command),
+                    cwd: self.command_cwd.take(),
+                    exit_code,
+                    started_at: self.started_at,
+                    finished_at,
+                    duration_ms: finished_at.saturating_sub(self.started_at),
+                };
+                if self.history.len() == COMMAND_HISTORY_LIMIT {
+                    self.history.pop_front();
+                }
+                self.history.push_back(command.clone());
+                self.finished.push(command);
+                self.phase = Phase::Idle;
+            }
+            _ => {}
+        }
+    }
+
+    fn on_cwd(&mut self, url: &[u8]) {
+        let url = String::from_utf8_lossy(url);
+        // file://host/path; the host is irrelevant for a local PTY.
+        let Some(rest) = url.strip_prefix("file://") else {
+            return;
+        };
+        let path = rest.find('/').map_or("/", |i| &rest[i..]);
+        let path = urlencoding::decode(path).map_or_else(|_| path.to_string(), |p| p.into_owned());
+        self.cwd = Some(path);
+    }
+}
+
+impl Perform for CommandTracker {
+    fn print(&mut self, c: char) {
+        if self.phase == Phase::Input && self.input.len() < MAX_COMMAND_LENGTH {
+            self.input.push(c);
+        }
+    }
+
+    fn execute(&mut self, byte: u8) {
+        if self.phase == Phase::Input && byte == 0x08 {
+            self.input.pop();
+        }
+    }
+
+    fn osc_dispatch(&mut self, params: &[&[u8]], _bell_terminated: bool) {
+        match params {
+            [b"133", rest @ ..] => self.on_prompt_mark(rest),
+            [b"7", url, ..] => self.on_cwd(url),
+            _ => {}
+        }
+    }
+}
+
+/// Follows OSC 133 (FinalTerm) prompt marks and OSC 7 working directory reports.
+pub struct ShellIntegration {
+    parser: Parser,
+    tracker: CommandTracker,
+}
+
+impl ShellIntegration {
+    pub fn new() -> Self {
+        ShellIntegration {
+            parser: Parser::new(),
+            tracker: CommandTracker::default(),
+        }
+    }
+
+    /// Feeds output and returns the commands that finished in it.
+    pub fn feed(&mut self, bytes: &[u8]) -> Vec<FinishedCommand> {
+        self.parser.advance(&mut self.tracker, bytes);
+        std::mem::take(&mut self.tracker.finished)
+    }
+
+    /// Last directory reported through OSC 7.
+    pub fn cwd(&self) -> Option<&str> {
+        self.tracker.cwd.as_deref()
+    }
+
+    pub fn history(&self) -> Vec<FinishedCommand> {
+        self.tracker.history.iter().cloned().collect()
+    }
+}
+
+fn shell_name(cmd: &CommandBuilder) -> Option<String> {
+    let program = cmd.get_argv().first()?;
+    Path::new(program)
+        .file_name()
+        .map(|name| name.to_string_lossy().into_owned())
+}
+
+fn write_file(path: &Path, contents: &str) -> Result<(), String> {
+    fs::write(path, contents).map_err(|err| format!("{}: {}", path.display(), err))
+}
+
+/// zsh startup files that load the user's own ones and then the integration.
+fn zsh_bootstrap(dir: &Path) -> Result<(), String> {
+    let user_dir = r#"${SCI_FI_UI_USER_ZDOTDIR:-$HOME}"#;
+    for file in [".zshenv", ".zprofile"] {
+        write_file(
+            &dir.join(file),
+            &format!(
+                "if [[ -r \"{user}/{file}\" ]]; then\n    __scifi_zdotdir=$ZDOTDIR\n    ZDOTDIR={user}\n    . \"$ZDOTDIR/{file}\"\n    ZDOTDIR=$__scifi_zdotdir\n    unset __scifi_zdotdir\nfi\n",
+                user = user_dir,
+                file = file
+            ),
+        )?;
+    }
+    write_file(
+        &dir.join(".zshrc"),
+        &format!(
+            "__scifi_integration=\"$ZDOTDIR/zsh-integration.zsh\"\nZDOTDIR={user}\nunset SCI_FI_UI_USER_ZDOTDIR\n[[ -r \"$ZDOTDIR/.zshrc\" ]] && . \"$ZDOTDIR/.zshrc\"\n. \"$__scifi_integration\"\nunset __scifi_integration\n",
+            user = user_dir
+        ),
+    )?;
+    write_file(&dir.join("zsh-integration.zsh"), ZSH_INTEGRATION)
+}
+
+/// Makes interactive bash and zsh sessions load the OSC 133 / OSC 7 hooks.
+/// Shells started with a script or `-c` are left alone.
+pub fn inject(app: &AppHandle, cmd: &mut CommandBuilder) -> Result<(), String> {
+    let Some(shell) = shell_name(cmd) else {
+        return Ok(());
+    };
+    let args = &cmd.get_argv()[1..];
+    let login = args.iter().any(|arg| arg == "-l" || arg == "--login");
+    if !args
+        .iter()
+        .all(|arg| arg == "-l" || arg == "--login" || arg == "-i")
+    {
+        return Ok(());
+    }
+
+    let dir: PathBuf = app
+        .path()
+        .app_cache_dir()
+        .map_err(|err| err.to_string())?
+        .join("shell-integration");
+
+    match shell.as_str() {
+        "bash" => {
+            fs::create_dir_all(&dir).map_err(|err| err.to_string())?;
+            let script = dir.join("bash-integration.sh");
+            write_file(&script, BASH_INTEGRATION)?;
+
+            // bash ignores --init-file for login shells, so the script sources the profile itself.
+            let argv = cmd.get_argv_mut();
+            argv.retain(|arg| arg != "-l" && arg != "--login");
+            argv.insert(1, OsString::from("--init-file"));
+            argv.insert(2, script.into_os_string());
+            if login {
+                cmd.env("SCI_FI_UI_LOGIN", "1");
+            }
+        }
+        "zsh" => {
+            let zdotdir = dir.join("zsh");
+            fs::create_dir_all(&zdotdir).map_err(|err| err.to_string())?;
+            zsh_bootstrap(&zdotdir)?;
+
+            if let Some(user_zdotdir) = cmd.get_env("ZDOTDIR").map(|dir| dir.to_os_string()) {
+                cmd.env("SCI_FI_UI_USER_ZDOTDIR", user_zdotdir);
+            }
+            cmd.env("ZDOTDIR", zdotdir.into_os_string());
+        }
+        _ => {}
+    }
+    Ok(())
+}
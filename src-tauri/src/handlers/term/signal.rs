@@ -0,0 +1,99 @@
+use portable_pty::MasterPty;
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Copy, Deserialize)]
+#[serde(rename_all = "UPPERCASE")]
+pub enum SessionSignal {
+    Sigint,
+    Sigterm,
+    Sigkill,
+    Sigtstp,
+    Sigcont,
+}
+
+#[cfg(unix)]
+impl SessionSignal {
+    fn number(self) -> libc::c_int {
+        match self {
+            SessionSignal::Sigint => libc::SIGINT,
+            SessionSignal::Sigterm => libc::SIGTERM,
+            SessionSignal::Sigkill => libc::SIGKILL,
+            SessionSignal::Sigtstp => libc::SIGTSTP,
+            SessionSignal::Sigcont => libc::SIGCONT,
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct SignalReport {
+    pub process_group: i32,
+    pub pids: Vec<i32>,
+}
+
+/// Members of a process group, read from `/proc/<pid>/stat`.
+#[cfg(target_os = "linux")]
+fn group_members(pgid: libc::pid_t) -> Vec<i32> {
+    let Ok(entries) = std::fs::read_dir("/proc") else {
+        return vec![pgid];
+    };
+
+    let mut pids: Vec<i32> = entries
+        .filter_map(|entry| entry.ok()?.file_name().to_str()?.parse::<i32>().ok())
+        .filter(|pid| {
+            let Ok(stat) = std::fs::read_to_string(format!("/proc/{}/stat", pid)) else {
+                return false;
+            };
+            // The command name may contain spaces and parentheses, so fields are counted from the last `)`.
+            stat.rfind(')')
+                .and_then(|end| stat[end + 1..].split_whitespace().nth(2))
+                .and_then(|pgrp| pgrp.parse::<i32>().ok())
+                == Some(pgid)
+        })
+        .collect();
+    pids.sort_unstable();
+    pids
+}
+
+#[cfg(all(unix, not(target_os = "linux")))]
+fn group_members(pgid: libc::pid_t) -> Vec<i32> {
+    vec![pgid]
+}
+
+/// The job that currently owns the terminal, as seen by `tcgetpgrp` on the
+/// master; for daemon sessions the daemon looks it up.
+#[cfg(unix)]
+pub fn foreground_process_group(master: &(dyn MasterPty + Send)) -> Result<libc::pid_t, String> {
+    master
+        .process_group_leader()
+        .ok_or_else(|| "Failed to get foreground process group".to_string())
+}
+
+/// Signals whatever job currently owns the terminal, not just the shell.
+#[cfg(unix)]
+pub fn signal_foreground(
+    master: &(dyn MasterPty + Send),
+    signal: SessionSignal,
+) -> Result<SignalReport, String> {
+    let pgid = foreground_process_group(master)?;
+    let pids = group_members(pgid);
+    if unsafe { libc::killpg(pgid, signal.number()) } != 0 {
+        return Err(format!(
+            "Failed to signal process group {}: {}",
+            pgid,
+            std::io::Error::last_os_error()
+        ));
+    }
+
+    Ok(SignalReport {
+        process_group: pgid,
+        pids,
+    })
+}
+
+#[cfg(not(unix))]
+pub fn signal_foreground(
+    _master: &(dyn MasterPty + Send),
+    _signal: SessionSignal,
+) -> Result<SignalReport, String> {
+    Err("Signals are not supported on this platform".to_string())
+}
@@ -0,0 +1,104 @@
+use serde::Serialize;
+use std::{
+    path::PathBuf,
+    sync::{Mutex, Weak},
+    thread,
+    time::Duration,
+};
+use sysinfo::{Pid, ProcessRefreshKind, ProcessesToUpdate, System, UpdateKind};
+use tauri::{AppHandle, Emitter};
+
+use super::session::{PtySession, SessionId};
+
+const STATUS_POLL_INTERVAL: Duration = Duration::from_millis(500);
+
+/// What runs in the foreground of a session, and where.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct SessionStatus {
+    pub pid: u32,
+    pub name: String,
+    pub cmdline: Vec<String>,
+    pub cwd: Option<String>,
+}
+
+#[derive(Serialize, Clone)]
+struct SessionStatusEvent {
+    session_id: SessionId,
+    #[serde(flatten)]
+    status: SessionStatus,
+}
+
+/// Falls back to the shell itself where there is no job control.
+fn foreground_pid(session: &PtySession) -> Option<u32> {
+    #[cfg(unix)]
+    {
+        let master = session.master.blocking_lock();
+        if let Ok(pgid) = super::signal::foreground_process_group(&**master) {
+            return Some(pgid as u32);
+        }
+    }
+    session.pid
+}
+
+fn read_status(sys: &mut System, pid: u32) -> Option<SessionStatus> {
+    let pid = Pid::from_u32(pid);
+    sys.refresh_processes_specifics(
+        ProcessesToUpdate::Some(&[pid]),
+        true,
+        ProcessRefreshKind::nothing()
+            .with_cmd(UpdateKind::Always)
+            .with_cwd(UpdateKind::Always),
+    );
+    let process = sys.process(pid)?;
+
+    Some(SessionStatus {
+        pid: pid.as_u32(),
+        name: process.name().to_string_lossy().into_owned(),
+        cmdline: process
+            .cmd()
+            .iter()
+            .map(|arg| arg.to_string_lossy().into_owned())
+            .collect(),
+        cwd: process.cwd().map(|cwd| cwd.to_string_lossy().into_owned()),
+    })
+}
+
+/// Polls the session's foreground process and emits `session_status` whenever
+/// it or its working directory changes. Stops once the session is dropped.
+pub fn spawn_status_poller(app: AppHandle, session_id: SessionId, session: Weak<PtySession>) {
+    thread::spawn(move || {
+        let mut sys = System::new();
+        loop {
+            thread::sleep(STATUS_POLL_INTERVAL);
+            let Some(session) = session.upgrade() else {
+                break;
+            };
+
+            let Some(status) = foreground_pid(&session).and_then(|pid| read_status(&mut sys, pid))
+            else {
+                continue;
+            };
+            if !update_status(&session.status, &status) {
+                continue;
+            }
+            session.output.links.lock().unwrap().cwd = status.cwd.as_ref().map(PathBuf::from);
+
+            if app
+                .emit("session_status", SessionStatusEvent { session_id, status })
+                .is_err()
+            {
+                break;
+            }
+        }
+    });
+}
+
+/// Stores `status` and reports whether it differs from the previous one.
+fn update_status(current: &Mutex<Option<SessionStatus>>, status: &SessionStatus) -> bool {
+    let mut current = current.lock().unwrap();
+    if current.as_ref() == Some(status) {
+        return false;
+    }
+    *current = Some(status.clone());
+    true
+}
@@ -0,0 +1,333 @@
+use serde::Serialize;
+use std::{
+    io::Read,
+    path::PathBuf,
+    sync::{
+        mpsc::{self, Receiver, RecvTimeoutError, Sender, SyncSender},
+        Arc, Condvar, Mutex,
+    },
+    thread,
+    time::{Duration, Instant},
+};
+use tauri::{AppHandle, Emitter, Manager};
+
+use super::{
+    decoder::{OutputDecoder, OutputEncoding, PtyData},
+    links::{LineLinks, LinkDetector},
+    recording::Recorder,
+    screen::Screen,
+    scrollback::{Scrollback, ScrollbackLimit},
+    session::SessionId,
+    shell_integration::{FinishedCommand, ShellIntegration},
+    triggers::{TriggerEngine, TriggerHit},
+};
+use crate::AppState;
+
+const READ_BUFFER_SIZE: usize = 8 * 1024;
+/// How many raw reads may queue up before the reader thread blocks and the
+/// kernel PTY buffer starts pushing back on the child.
+const READ_QUEUE_DEPTH: usize = 16;
+/// Small reads arriving within this window are merged into one event.
+const COALESCE_WINDOW: Duration = Duration::from_millis(4);
+const MAX_CHUNK_SIZE: usize = 64 * 1024;
+/// Output the webview has not acknowledged yet; above this the pump waits.
+const HIGH_WATERMARK: usize = 256 * 1024;
+const LOW_WATERMARK: usize = 64 * 1024;
+/// A webview that stops acknowledging (reload, crash) must not freeze the shell forever.
+const ACK_TIMEOUT: Duration = Duration::from_secs(2);
+/// Chunks the link scanner may fall behind by; older ones have their lines
+/// counted but not scanned.
+const MAX_LINK_BACKLOG: usize = 16;
+
+#[derive(Serialize, Clone)]
+struct PtyOutput {
+    session_id: SessionId,
+    data: PtyData,
+    bytes: usize,
+    /// Stream position just past this chunk, see [`SessionOutput::snapshot`].
+    offset: u64,
+}
+
+/// A command reported finished by the shell's OSC 133 prompt marks.
+#[derive(Serialize, Clone)]
+struct CommandFinished {
+    session_id: SessionId,
+    #[serde(flatten)]
+    command: FinishedCommand,
+}
+
+#[derive(Serialize, Clone)]
+struct TerminalTrigger {
+    session_id: SessionId,
+    #[serde(flatten)]
+    hit: TriggerHit,
+}
+
+#[derive(Serialize, Clone)]
+struct TerminalLinks {
+    session_id: SessionId,
+    #[serde(flatten)]
+    links: LineLinks,
+}
+
+/// Everything recorded for a session's output; replayed on `attach_session`.
+#[derive(Serialize, Clone)]
+pub struct OutputSnapshot {
+    data: PtyData,
+    offset: u64,
+    /// Absolute number of the snapshot's first line, as used by `terminal_links`.
+    first_line: u64,
+}
+
+/// Output-side state shared between a session and its pump thread.
+pub struct SessionOutput {
+    pub encoding: OutputEncoding,
+    pub flow: FlowControl,
+    pub scrollback: Mutex<Scrollback>,
+    pub recorder: Mutex<Option<Recorder>>,
+    pub screen: Mutex<Screen>,
+    pub integration: Mutex<ShellIntegration>,
+    pub triggers: Mutex<TriggerEngine>,
+    pub links: Mutex<LinkDetector>,
+}
+
+impl SessionOutput {
+    pub fn new(
+        encoding: OutputEncoding,
+        limit: ScrollbackLimit,
+        rows: u16,
+        cols: u16,
+        triggers: TriggerEngine,
+    ) -> Self {
+        SessionOutput {
+            encoding,
+            flow: FlowControl::new(),
+            scrollback: Mutex::new(Scrollback::new(limit)),
+            recorder: Mutex::new(None),
+            screen: Mutex::new(Screen::new(rows, cols)),
+            integration: Mutex::new(ShellIntegration::new()),
+            triggers: Mutex::new(triggers),
+            links: Mutex::new(LinkDetector::default()),
+        }
+    }
+
+    /// Takes in output a previous run of the app already showed, without
+    /// emitting events for it again. Triggers and link detection skip it: old
+    /// matches would only start cooldowns that swallow the next real hit.
+    pub fn restore(&self, history: &[u8]) {
+        self.scrollback.lock().unwrap().push(history);
+        self.screen.lock().unwrap().feed(history);
+        self.integration.lock().unwrap().feed(history);
+        self.links.lock().unwrap().skip(history);
+    }
+
+    /// Hands output to an active recording; a recording that fails to write is dropped.
+    fn record(&self, chunk: &[u8]) {
+        let mut recorder = self.recorder.lock().unwrap();
+        if let Some(active) = recorder.as_mut() {
+            if active.output(chunk).is_err() {
+                *recorder = None;
+            }
+        }
+    }
+
+    pub fn record_resize(&self, cols: u16, rows: u16) {
+        let mut recorder = self.recorder.lock().unwrap();
+        if let Some(active) = recorder.as_mut() {
+            if active.resize(cols, rows).is_err() {
+                *recorder = None;
+            }
+        }
+    }
+
+    /// Live `pty_output` events with an `offset` at or below the snapshot's
+    /// are already part of it and should be skipped by the attaching widget.
+    pub fn snapshot(&self) -> OutputSnapshot {
+        let scrollback = self.scrollback.lock().unwrap();
+        OutputSnapshot {
+            data: OutputDecoder::new(self.encoding).decode(&scrollback.contents()),
+            offset: scrollback.offset(),
+            first_line: scrollback.first_line(),
+        }
+    }
+}
+
+/// Counts bytes emitted to the webview but not yet written by the terminal widget.
+pub struct FlowControl {
+    unacked: Mutex<usize>,
+    drained: Condvar,
+}
+
+impl FlowControl {
+    pub fn new() -> Self {
+        FlowControl {
+            unacked: Mutex::new(0),
+            drained: Condvar::new(),
+        }
+    }
+
+    pub fn ack(&self, bytes: usize) {
+        let mut unacked = self.unacked.lock().unwrap();
+        *unacked = unacked.saturating_sub(bytes);
+        if *unacked <= LOW_WATERMARK {
+            self.drained.notify_all();
+        }
+    }
+
+    fn sent(&self, bytes: usize) {
+        *self.unacked.lock().unwrap() += bytes;
+    }
+
+    /// Forgets output sent to a webview that has since gone away.
+    pub fn reset(&self) {
+        *self.unacked.lock().unwrap() = 0;
+        self.drained.notify_all();
+    }
+
+    fn wait_for_capacity(&self) {
+        let unacked = self.unacked.lock().unwrap();
+        if *unacked <= HIGH_WATERMARK {
+            return;
+        }
+
+        let (mut unacked, timeout) = self
+            .drained
+            .wait_timeout_while(unacked, ACK_TIMEOUT, |unacked| *unacked > LOW_WATERMARK)
+            .unwrap();
+        if timeout.timed_out() {
+            *unacked = 0;
+        }
+    }
+}
+
+/// Starts the reader and pump threads that push PTY output to the webview as
+/// `pty_output` events until the PTY reports EOF. The returned receiver
+/// disconnects once the pump has emitted its last chunk.
+pub fn spawn_output_pump(
+    app: AppHandle,
+    session_id: SessionId,
+    reader: Box<dyn Read + Send>,
+    output: Arc<SessionOutput>,
+) -> Receiver<()> {
+    let (tx, rx) = mpsc::sync_channel(READ_QUEUE_DEPTH);
+    let (done_tx, done_rx) = mpsc::channel();
+
+    thread::spawn(move || read_loop(reader, tx));
+    thread::spawn(move || {
+        pump_loop(app, session_id, rx, output);
+        drop(done_tx);
+    });
+
+    done_rx
+}
+
+fn read_loop(mut reader: Box<dyn Read + Send>, tx: SyncSender<Vec<u8>>) {
+    let mut buf = [0u8; READ_BUFFER_SIZE];
+    loop {
+        match reader.read(&mut buf) {
+            // EOF, or EIO on Linux once the child side has gone away.
+            Ok(0) | Err(_) => break,
+            Ok(n) => {
+                if tx.send(buf[..n].to_vec()).is_err() {
+                    break;
+                }
+            }
+        }
+    }
+}
+
+/// Finds links on a thread of its own: checking path candidates against the
+/// filesystem must not hold up output. Stops once the pump drops the sender.
+fn spawn_link_scanner(
+    app: AppHandle,
+    session_id: SessionId,
+    output: Arc<SessionOutput>,
+) -> Sender<(Vec<u8>, Option<PathBuf>)> {
+    let (tx, rx) = mpsc::channel::<(Vec<u8>, Option<PathBuf>)>();
+    thread::spawn(move || {
+        while let Ok(first) = rx.recv() {
+            let mut batch = vec![first];
+            batch.extend(rx.try_iter());
+            let skipped = batch.len().saturating_sub(MAX_LINK_BACKLOG);
+
+            for (i, (chunk, shell_cwd)) in batch.into_iter().enumerate() {
+                let mut detector = output.links.lock().unwrap();
+                if i < skipped {
+                    detector.skip(&chunk);
+                    continue;
+                }
+                let found = detector.feed(&chunk, shell_cwd.as_deref());
+                drop(detector);
+                for links in found {
+                    let _ = app.emit("terminal_links", TerminalLinks { session_id, links });
+                }
+            }
+        }
+    });
+    tx
+}
+
+fn pump_loop(
+    app: AppHandle,
+    session_id: SessionId,
+    rx: Receiver<Vec<u8>>,
+    output: Arc<SessionOutput>,
+) {
+    let mut decoder = OutputDecoder::new(output.encoding);
+    let links = spawn_link_scanner(app.clone(), session_id, output.clone());
+
+    while let Ok(mut chunk) = rx.recv() {
+        let deadline = Instant::now() + COALESCE_WINDOW;
+        while chunk.len() < MAX_CHUNK_SIZE {
+            let remaining = deadline.saturating_duration_since(Instant::now());
+            match rx.recv_timeout(remaining) {
+                Ok(more) => chunk.extend_from_slice(&more),
+                Err(RecvTimeoutError::Timeout) | Err(RecvTimeoutError::Disconnected) => break,
+            }
+        }
+
+        output.flow.wait_for_capacity();
+
+        let offset = output.scrollback.lock().unwrap().push(&chunk);
+        output.record(&chunk);
+        output.screen.lock().unwrap().feed(&chunk);
+        let finished = output.integration.lock().unwrap().feed(&chunk);
+        for command in finished {
+            app.state::<AppState>().history.record(&command);
+            let _ = app.emit(
+                "command_finished",
+                CommandFinished {
+                    session_id,
+                    command,
+                },
+            );
+        }
+        let hits = output.triggers.lock().unwrap().feed(&chunk);
+        for hit in hits {
+            let _ = app.emit("terminal_trigger", TerminalTrigger { session_id, hit });
+        }
+        let shell_cwd = output.integration.lock().unwrap().cwd().map(PathBuf::from);
+        let _ = links.send((chunk.clone(), shell_cwd));
+        let data = decoder.decode(&chunk);
+        if matches!(&data, PtyData::Text(text) if text.is_empty()) {
+            continue;
+        }
+
+        let bytes = chunk.len();
+        output.flow.sent(bytes);
+        if app
+            .emit(
+                "pty_output",
+                PtyOutput {
+                    session_id,
+                    data,
+                    bytes,
+                    offset,
+                },
+            )
+            .is_err()
+        {
+            break;
+        }
+    }
+}
@@ -0,0 +1,137 @@
+use regex::Regex;
+use serde::Serialize;
+use std::time::{Duration, Instant};
+
+use super::ansi::strip_line;
+use crate::handlers::config::triggers::TriggerRule;
+
+/// A line longer than this is matched in pieces rather than buffered forever.
+const MAX_PENDING_LINE: usize = 64 * 1024;
+
+#[derive(Debug, Clone, Serialize)]
+pub struct TriggerHit {
+    pub rule: String,
+    pub line: String,
+    pub matched: String,
+    pub speak: Option<String>,
+    pub voice: Option<String>,
+}
+
+struct ActiveRule {
+    rule: TriggerRule,
+    regex: Regex,
+    last_fired: Option<Instant>,
+}
+
+/// Matches a session's output line by line against the trigger rules of its
+/// profile, optionally narrowed down to a few of them for this session alone.
+#[derive(Default)]
+pub struct TriggerEngine {
+    /// Profile the session was started from; `None` for replays, which never trigger.
+    profile: Option<String>,
+    /// Names of the rules this session runs; `None` runs all of its profile's.
+    scope: Option<Vec<String>>,
+    rules: Vec<ActiveRule>,
+    pending: Vec<u8>,
+}
+
+impl TriggerEngine {
+    pub fn new(profile: &str, rules: &[TriggerRule]) -> Self {
+        let mut engine = TriggerEngine {
+            profile: Some(profile.to_string()),
+            ..Default::default()
+        };
+        engine.set_rules(rules);
+        engine
+    }
+
+    pub fn profile(&self) -> Option<&str> {
+        self.profile.as_deref()
+    }
+
+    /// Limits the session to the rules named in `scope`, or lifts the limit on `None`.
+    pub fn set_scope(&mut self, scope: Option<Vec<String>>, rules: &[TriggerRule]) {
+        self.scope = scope;
+        self.set_rules(rules);
+    }
+
+    /// Swaps in new rules; a rule that keeps its name keeps its cooldown.
+    pub fn set_rules(&mut self, rules: &[TriggerRule]) {
+        let Some(profile) = &self.profile else {
+            return;
+        };
+
+        let mut previous = std::mem::take(&mut self.rules);
+        self.rules = rules
+            .iter()
+            .filter(|rule| rule.applies_to(profile))
+            .filter(|rule| {
+                self.scope
+                    .as_ref()
+                    .is_none_or(|names| names.contains(&rule.name))
+            })
+            .filter_map(|rule| {
+                let regex = rule.regex().ok()?;
+                let last_fired = previous
+                    .iter_mut()
+                    .find(|active| active.rule.name == rule.name)
+                    .and_then(|active| active.last_fired.take());
+                Some(ActiveRule {
+                    rule: rule.clone(),
+                    regex,
+                    last_fired,
+                })
+            })
+            .collect();
+    }
+
+    /// Feeds output and returns the rules that fired on the lines it completed.
+    pub fn feed(&mut self, bytes: &[u8]) -> Vec<TriggerHit> {
+        if self.rules.is_empty() {
+            return Vec::new();
+        }
+
+        let mut hits = Vec::new();
+        let mut rest = bytes;
+        while let Some(end) = rest.iter().position(|&b| b == b'\n') {
+            self.pending.extend_from_slice(&rest[..end]);
+            rest = &rest[end + 1..];
+            let line = std::mem::take(&mut self.pending);
+            self.match_line(&line, &mut hits);
+        }
+
+        self.pending.extend_from_slice(rest);
+        if self.pending.len() > MAX_PENDING_LINE {
+            let line = std::mem::take(&mut self.pending);
+            self.match_line(&line, &mut hits);
+        }
+        hits
+    }
+
+    fn match_line(&mut self, raw: &[u8], hits: &mut Vec<TriggerHit>) {
+        let line = strip_line(raw);
+        let now = Instant::now();
+
+        for active in &mut self.rules {
+            let cooldown = Duration::from_millis(active.rule.cooldown_ms);
+            if active
+                .last_fired
+                .is_some_and(|fired| now.duration_since(fired) < cooldown)
+            {
+                continue;
+            }
+            let Some(found) = active.regex.find(&line) else {
+                continue;
+            };
+
+            active.last_fired = Some(now);
+            hits.push(TriggerHit {
+                rule: active.rule.name.clone(),
+                line: line.trim_end().to_string(),
+                matched: found.as_str().to_string(),
+                speak: active.rule.speak.clone(),
+                voice: active.rule.voice.clone(),
+            });
+        }
+    }
+}
@@ -1,59 +1,58 @@
 mod handlers;
 mod utils;
 use handlers::ai::audio::generate_audio;
+use handlers::config::monitors::{monitor_configure, monitor_status, read_monitor_configs};
 use handlers::config::pollinations_ai::{get_api_token, save_api_token};
-use handlers::events::{
-    start_cpu_monitor, start_memory_monitor, start_ping_monitor, start_process_monitor,
+use handlers::config::shell_profiles::{
+    delete_shell_profile, list_shell_profiles, save_shell_profile,
 };
+use handlers::config::triggers::{list_trigger_rules, save_trigger_rules};
+use handlers::events::{monitor_hold, monitor_release, query_metrics, Monitors};
+use handlers::exec::{cancel_exec, exec_command, ExecJobs};
 use handlers::file_system::{get_drives, get_parent_dir, list_dir};
+use handlers::term::history::CommandHistory;
+use handlers::term::session::SessionManager;
 use handlers::term::{
-    async_create_shell, async_read_from_pty, async_resize_pty, async_write_to_pty,
+    ack_pty_output, async_close_shell, async_create_shell, async_resize_pty, async_write_to_pty,
+    attach_session, broadcast_input, export_session, get_broadcast_group, get_screen_snapshot,
+    get_session_fallback, get_session_status, list_session_commands, open_link, replay_recording,
+    search_session, set_session_triggers, signal_session, start_recording, stop_recording,
+    suggest_commands,
 };
-use portable_pty::{native_pty_system, PtyPair, PtySize};
-use std::io::{Read, Write};
-use std::{io::BufReader, sync::Arc};
-use tauri::async_runtime::Mutex as AsyncMutex;
 use tauri::Manager;
 use tauri_plugin_store;
 
 pub struct AppState {
-    pub pty_pair: Arc<AsyncMutex<PtyPair>>,
-    pub writer: Arc<AsyncMutex<Box<dyn Write + Send>>>,
-    pub reader: Arc<AsyncMutex<BufReader<Box<dyn Read + Send>>>>,
+    pub sessions: SessionManager,
+    pub jobs: ExecJobs,
+    pub history: CommandHistory,
+    pub monitors: Monitors,
 }
 
 #[cfg_attr(mobile, tauri::mobile_entry_point)]
 pub fn run() {
-    let pty_system = native_pty_system();
-    let pty_pair = pty_system
-        .openpty(PtySize {
-            rows: 40,
-            cols: 40,
-            pixel_width: 0,
-            pixel_height: 0,
-        })
-        .unwrap();
-
-    let reader = pty_pair.master.try_clone_reader().unwrap();
-    let writer = pty_pair.master.take_writer().unwrap();
-
     tauri::Builder::default()
         .plugin(tauri_plugin_store::Builder::default().build())
         .plugin(tauri_plugin_dialog::init())
         .manage(AppState {
-            pty_pair: Arc::new(AsyncMutex::new(pty_pair)),
-            writer: Arc::new(AsyncMutex::new(writer)),
-            reader: Arc::new(AsyncMutex::new(BufReader::new(reader))),
+            sessions: SessionManager::new(),
+            jobs: ExecJobs::default(),
+            history: CommandHistory::new(),
+            monitors: Monitors::new(),
         })
         .setup(|app| {
             let main_window = app.get_webview_window("main").unwrap();
 
             let handle = app.handle();
 
-            start_cpu_monitor(handle.clone());
-            start_memory_monitor(handle.clone());
-            start_process_monitor(handle.clone());
-            start_ping_monitor(handle.clone());
+            let monitors = read_monitor_configs(handle).unwrap_or_default();
+            app.state::<AppState>()
+                .monitors
+                .start(handle.clone(), &monitors);
+
+            // Shells kept alive by the session daemon are back before any widget attaches.
+            #[cfg(unix)]
+            tauri::async_runtime::block_on(app.state::<AppState>().sessions.restore(handle));
 
             let _app_handle = app.handle();
             std::thread::spawn(move || {
@@ -67,6 +66,11 @@ pub fn run() {
         .invoke_handler(tauri::generate_handler![
             save_api_token,
             get_api_token,
+            list_shell_profiles,
+            save_shell_profile,
+            delete_shell_profile,
+            list_trigger_rules,
+            save_trigger_rules,
             get_parent_dir,
             get_drives,
             list_dir,
@@ -74,8 +78,44 @@ pub fn run() {
             async_write_to_pty,
             async_resize_pty,
             async_create_shell,
-            async_read_from_pty
+            async_close_shell,
+            attach_session,
+            ack_pty_output,
+            start_recording,
+            stop_recording,
+            replay_recording,
+            get_screen_snapshot,
+            search_session,
+            list_session_commands,
+            suggest_commands,
+            signal_session,
+            set_session_triggers,
+            get_session_status,
+            get_session_fallback,
+            broadcast_input,
+            get_broadcast_group,
+            export_session,
+            open_link,
+            exec_command,
+            cancel_exec,
+            monitor_status,
+            monitor_configure,
+            monitor_hold,
+            monitor_release,
+            query_metrics
         ])
         .run(tauri::generate_context!())
         .expect("error while running tauri application");
 }
+
+/// Entry point of the `sci-fi-ui-daemon` binary, which keeps shells running across app restarts.
+#[cfg(unix)]
+pub fn run_daemon() {
+    handlers::term::daemon::server::run();
+}
+
+#[cfg(not(unix))]
+pub fn run_daemon() {
+    eprintln!("The session daemon is only available on Unix");
+    std::process::exit(1);
+}
@@ -0,0 +1,59 @@
+import { invoke } from '@tauri-apps/api/core'
+import { listen } from '@tauri-apps/api/event'
+
+export interface ExecOptions {
+	args?: string[]
+	cwd?: string
+	env?: Record<string, string>
+	timeoutMs?: number
+	onOutput?: (stream: 'stdout' | 'stderr', data: string) => void
+}
+
+export interface ExecResult {
+	job_id: string
+	exit_code: number | null
+	signal: number | null
+	timed_out: boolean
+	cancelled: boolean
+	stdout: { text: string; truncated: boolean }
+	stderr: { text: string; truncated: boolean }
+}
+
+export interface ExecJob {
+	id: string
+	result: Promise<ExecResult>
+	cancel: () => Promise<void>
+}
+
+/** Runs a program outside the terminal; `onOutput` receives its output as it arrives. */
+export function execCommand(program: string, options: ExecOptions = {}): ExecJob {
+	const id = crypto.randomUUID()
+	const result = (async () => {
+		const unlisten = options.onOutput
+			? await listen<{ job_id: string; stream: 'stdout' | 'stderr'; data: string }>(
+					'exec_output',
+					event => {
+						if (event.payload.job_id === id) {
+							options.onOutput?.(event.payload.stream, event.payload.data)
+						}
+					},
+				)
+			: null
+		try {
+			return await invoke<ExecResult>('exec_command', {
+				jobId: id,
+				command: {
+					program,
+					args: options.args ?? [],
+					cwd: options.cwd ?? null,
+					env: options.env ?? {},
+					timeout_ms: options.timeoutMs ?? null,
+				},
+			})
+		} finally {
+			unlisten?.()
+		}
+	})()
+
+	return { id, result, cancel: () => invoke('cancel_exec', { jobId: id }) }
+}
@@ -0,0 +1,103 @@
+import { invoke } from '@tauri-apps/api/core'
+
+export type MonitorName = 'cpu' | 'memory' | 'process' | 'ping' | 'disk'
+
+export interface MonitorStatus {
+	name: MonitorName
+	interval_ms: number
+	enabled: boolean
+}
+
+export function monitorStatus(): Promise<MonitorStatus[]> {
+	return invoke<MonitorStatus[]>('monitor_status')
+}
+
+export interface MonitorOptions {
+	enabled: boolean
+	intervalMs?: number
+}
+
+/** Turns a monitor on or off, e.g. when its widget is hidden; the choice persists across restarts. */
+export function configureMonitor(
+	name: MonitorName,
+	{ enabled, intervalMs }: MonitorOptions,
+): Promise<MonitorStatus> {
+	return invoke<MonitorStatus>('monitor_configure', {
+		name,
+		enabled,
+		intervalMs: intervalMs ?? null,
+	})
+}
+
+// Widgets currently showing each monitor; several can share one.
+const holders = new Map<MonitorName, number>()
+
+// Runtime demand only: the user's enabled setting is left alone, and a monitor
+// they turned off stays off however many widgets hold it.
+function setHeld(name: MonitorName, held: boolean) {
+	invoke(held ? 'monitor_hold' : 'monitor_release', { name }).catch(error =>
+		console.error(`Error updating the ${name} monitor:`, error),
+	)
+}
+
+/**
+ * Keeps `name` sampling while a widget shows it. Call from `onMount`; the
+ * returned function releases it again once the widget is destroyed, and the
+ * monitor stops when no widget is left.
+ */
+export function holdMonitor(name: MonitorName): () => void {
+	const count = holders.get(name) ?? 0
+	holders.set(name, count + 1)
+	if (count === 0 && !document.hidden) setHeld(name, true)
+
+	let released = false
+	return () => {
+		if (released) return
+		released = true
+		const remaining = (holders.get(name) ?? 1) - 1
+		holders.set(name, remaining)
+		if (remaining === 0 && !document.hidden) setHeld(name, false)
+	}
+}
+
+// Nothing is on screen while the window is hidden, e.g. minimised.
+document.addEventListener('visibilitychange', () => {
+	for (const [name, count] of holders) {
+		if (count > 0) setHeld(name, !document.hidden)
+	}
+})
+
+export interface MetricPoint {
+	time: number
+	value: number
+	min: number
+	max: number
+}
+
+export interface MetricSeries {
+	metric: string
+	resolution_ms: number
+	points: MetricPoint[]
+}
+
+export function queryMetrics(
+	metric: string,
+	from: number,
+	to: number,
+	resolution?: number,
+): Promise<MetricSeries> {
+	return invoke<MetricSeries>('query_metrics', {
+		metric,
+		from,
+		to,
+		resolution: resolution ?? null,
+	})
+}
+
+/** The last `count` one-second values of `metric`, zero-padded at the front, for backfilling charts. */
+export async function recentValues(metric: string, count: number): Promise<number[]> {
+	const now = Date.now()
+	const { points } = await queryMetrics(metric, now - count * 1000, now, 1000)
+	const values = points.slice(-count).map(point => point.value)
+	return [...Array(count - values.length).fill(0), ...values]
+}